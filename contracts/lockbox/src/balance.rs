@@ -1,9 +1,12 @@
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::convert::TryFrom;
 
-use cosmwasm_std::Coin;
-use cw20::{Balance, Cw20CoinVerified};
+use cosmwasm_std::{Coin, StdError, StdResult, Uint128};
+use cw20::{Balance, Cw20CoinVerified, Denom};
 
+/// Multiset of native and cw20 tokens, kept sorted by denom / address,
+/// with a single non-zero entry per token
 #[derive(Serialize, Deserialize, Clone, PartialEq, JsonSchema, Debug, Default)]
 pub struct GenericBalance {
     pub native: Vec<Coin>,
@@ -11,43 +14,108 @@ pub struct GenericBalance {
 }
 
 impl GenericBalance {
-    pub fn add_tokens(&mut self, add: Balance) {
+    /// Adds the given tokens, fails without changes on overflow
+    pub fn add_tokens(&mut self, add: Balance) -> StdResult<()> {
+        let mut sum = self.clone();
         match add {
-            Balance::Native(balance) => {
-                for token in balance.0 {
-                    let index = self.native.iter().enumerate().find_map(|(i, exist)| {
-                        if exist.denom == token.denom {
-                            Some(i)
-                        } else {
-                            None
-                        }
-                    });
-                    match index {
-                        Some(idx) => self.native[idx].amount += token.amount,
-                        None => self.native.push(token),
-                    }
+            Balance::Native(balance) => sum.native.extend(balance.0),
+            Balance::Cw20(token) => sum.cw20.push(token),
+        };
+
+        sum.normalize()?;
+        *self = sum;
+        Ok(())
+    }
+
+    /// Adds all tokens of another balance
+    pub fn merge(&mut self, other: GenericBalance) -> StdResult<()> {
+        if !other.native.is_empty() {
+            self.add_tokens(Balance::from(other.native))?;
+        }
+        for token in other.cw20 {
+            self.add_tokens(Balance::Cw20(token))?;
+        }
+        Ok(())
+    }
+
+    /// Removes the given tokens, fails without changes if any amount is not available
+    pub fn sub_tokens(&mut self, sub: &GenericBalance) -> StdResult<()> {
+        let mut left = self.clone();
+        for token in sub.native.iter() {
+            let available = left.amount_of(&Denom::Native(token.denom.clone()));
+            let amount = available.checked_sub(token.amount)?;
+            if let Some(exist) = left.native.iter_mut().find(|c| c.denom == token.denom) {
+                exist.amount = amount;
+            }
+        }
+        for token in sub.cw20.iter() {
+            let available = left.amount_of(&Denom::Cw20(token.address.clone()));
+            let amount = available.checked_sub(token.amount)?;
+            if let Some(exist) = left.cw20.iter_mut().find(|c| c.address == token.address) {
+                exist.amount = amount;
+            }
+        }
+
+        left.normalize()?;
+        *self = left;
+        Ok(())
+    }
+
+    /// Returns the amount held of the given token
+    pub fn amount_of(&self, denom: &Denom) -> Uint128 {
+        match denom {
+            Denom::Native(denom) => self
+                .native
+                .iter()
+                .filter(|c| &c.denom == denom)
+                .map(|c| c.amount)
+                .sum(),
+            Denom::Cw20(address) => self
+                .cw20
+                .iter()
+                .filter(|c| c.address == *address)
+                .map(|c| c.amount)
+                .sum(),
+        }
+    }
+
+    /// Sorts the tokens, merging duplicates and dropping zero amounts,
+    /// may leave the balance partially merged on overflow
+    fn normalize(&mut self) -> StdResult<()> {
+        self.native.sort_by(|a, b| a.denom.cmp(&b.denom));
+        let mut native: Vec<Coin> = Vec::with_capacity(self.native.len());
+        for token in self.native.drain(..) {
+            match native.last_mut() {
+                Some(prev) if prev.denom == token.denom => {
+                    prev.amount = prev.amount.checked_add(token.amount)?
                 }
+                _ => native.push(token),
             }
-            Balance::Cw20(token) => {
-                let index = self.cw20.iter().enumerate().find_map(|(i, exist)| {
-                    if exist.address == token.address {
-                        Some(i)
-                    } else {
-                        None
-                    }
-                });
-                match index {
-                    Some(idx) => self.cw20[idx].amount += token.amount,
-                    None => self.cw20.push(token),
+        }
+        native.retain(|c| !c.amount.is_zero());
+        self.native = native;
+
+        self.cw20.sort_by(|a, b| a.address.cmp(&b.address));
+        let mut cw20: Vec<Cw20CoinVerified> = Vec::with_capacity(self.cw20.len());
+        for token in self.cw20.drain(..) {
+            match cw20.last_mut() {
+                Some(prev) if prev.address == token.address => {
+                    prev.amount = prev.amount.checked_add(token.amount)?
                 }
+                _ => cw20.push(token),
             }
-        };
+        }
+        cw20.retain(|c| !c.amount.is_zero());
+        self.cw20 = cw20;
+        Ok(())
     }
 }
 
-impl From<Balance> for GenericBalance {
-    fn from(balance: Balance) -> GenericBalance {
-        match balance {
+impl TryFrom<Balance> for GenericBalance {
+    type Error = StdError;
+
+    fn try_from(balance: Balance) -> StdResult<GenericBalance> {
+        let mut balance = match balance {
             Balance::Native(balance) => GenericBalance {
                 native: balance.0,
                 cw20: vec![],
@@ -56,6 +124,127 @@ impl From<Balance> for GenericBalance {
                 native: vec![],
                 cw20: vec![token],
             },
+        };
+        balance.normalize()?;
+        Ok(balance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::{coin, to_vec, Addr};
+    use proptest::prelude::*;
+
+    fn native_strategy() -> impl Strategy<Value = Vec<Coin>> {
+        prop::collection::vec(("[a-d]", 0..1000u128), 0..8)
+            .prop_map(|coins| coins.iter().map(|(d, a)| coin(*a, d)).collect())
+    }
+
+    fn cw20_strategy() -> impl Strategy<Value = Vec<Cw20CoinVerified>> {
+        prop::collection::vec(("cw20[a-d]", 0..1000u128), 0..8).prop_map(|coins| {
+            coins
+                .into_iter()
+                .map(|(address, amount)| Cw20CoinVerified {
+                    address: Addr::unchecked(address),
+                    amount: amount.into(),
+                })
+                .collect()
+        })
+    }
+
+    fn balances(native: &[Coin], cw20: &[Cw20CoinVerified]) -> Vec<Balance> {
+        native
+            .iter()
+            .map(|c| Balance::from(vec![c.clone()]))
+            .chain(cw20.iter().cloned().map(Balance::Cw20))
+            .collect()
+    }
+
+    fn build(balances: impl IntoIterator<Item = Balance>) -> GenericBalance {
+        let mut balance = GenericBalance::default();
+        for add in balances {
+            balance.add_tokens(add).unwrap();
+        }
+        balance
+    }
+
+    fn assert_normalized(balance: &GenericBalance) {
+        assert!(balance.native.windows(2).all(|w| w[0].denom < w[1].denom));
+        assert!(balance.native.iter().all(|c| !c.amount.is_zero()));
+        assert!(balance.cw20.windows(2).all(|w| w[0].address < w[1].address));
+        assert!(balance.cw20.iter().all(|c| !c.amount.is_zero()));
+    }
+
+    proptest! {
+        #[test]
+        fn from_normalizes(native in native_strategy()) {
+            let balance = GenericBalance::try_from(Balance::from(native.clone())).unwrap();
+            assert_normalized(&balance);
+            for token in native.iter() {
+                let expected: Uint128 = native
+                    .iter()
+                    .filter(|c| c.denom == token.denom)
+                    .map(|c| c.amount)
+                    .sum();
+                prop_assert_eq!(expected, balance.amount_of(&Denom::Native(token.denom.clone())));
+            }
         }
+
+        #[test]
+        fn add_is_order_independent(native in native_strategy(), cw20 in cw20_strategy()) {
+            let adds = balances(&native, &cw20);
+            let balance = build(adds.clone());
+            let reversed = build(adds.into_iter().rev());
+
+            assert_normalized(&balance);
+            prop_assert_eq!(&balance, &reversed);
+            prop_assert_eq!(to_vec(&balance).unwrap(), to_vec(&reversed).unwrap());
+        }
+
+        #[test]
+        fn merge_then_sub_roundtrips(
+            native in native_strategy(),
+            cw20 in cw20_strategy(),
+            other_native in native_strategy(),
+            other_cw20 in cw20_strategy(),
+        ) {
+            let balance = build(balances(&native, &cw20));
+            let other = build(balances(&other_native, &other_cw20));
+
+            let mut merged = balance.clone();
+            merged.merge(other.clone()).unwrap();
+            assert_normalized(&merged);
+
+            merged.sub_tokens(&other).unwrap();
+            prop_assert_eq!(balance, merged);
+        }
+
+        #[test]
+        fn sub_unavailable_fails_unchanged(native in native_strategy(), cw20 in cw20_strategy()) {
+            let balance = build(balances(&native, &cw20));
+            let mut over = balance.clone();
+            over.add_tokens(Balance::from(vec![coin(1, "a")])).unwrap();
+
+            let mut left = balance.clone();
+            prop_assert!(left.sub_tokens(&over).is_err());
+            prop_assert_eq!(balance, left);
+        }
+    }
+
+    #[test]
+    fn add_overflow() {
+        let mut balance =
+            GenericBalance::try_from(Balance::from(vec![coin(u128::MAX, "a")])).unwrap();
+        let res = balance.add_tokens(Balance::from(vec![coin(1, "a"), coin(1, "b")]));
+        assert!(res.is_err());
+        assert_eq!(Uint128::MAX, balance.amount_of(&Denom::Native("a".into())));
+        assert_eq!(
+            Uint128::zero(),
+            balance.amount_of(&Denom::Native("b".into()))
+        );
+
+        let res = GenericBalance::try_from(Balance::from(vec![coin(u128::MAX, "a"), coin(1, "a")]));
+        assert!(res.is_err());
     }
 }
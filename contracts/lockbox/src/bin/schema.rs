@@ -1,11 +1,12 @@
 use cosmwasm_schema::write_api;
 
-use cw_disper_lockbox::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use cw_disper_lockbox::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, SudoMsg};
 
 fn main() {
     write_api! {
         instantiate: InstantiateMsg,
         execute: ExecuteMsg,
         query: QueryMsg,
+        sudo: SudoMsg,
     }
 }
@@ -1,20 +1,53 @@
 use cosmwasm_std::{
-    entry_point, from_binary, to_binary, Addr, BankMsg, Binary, Deps, DepsMut, Env, MessageInfo,
-    Order, Response, StdResult, SubMsg, Timestamp, WasmMsg,
+    coin, entry_point, from_binary, to_binary, Addr, BankMsg, Binary, Coin, CosmosMsg, Decimal,
+    Deps, DepsMut, Env, Event, MessageInfo, Order, Reply, Response, StdError, StdResult, Storage,
+    SubMsg, SubMsgResult, Timestamp, Uint128, WasmMsg,
 };
 
 use crate::balance::GenericBalance;
 use crate::error::ContractError;
-use crate::msg::{AllLocksResponse, ExecuteMsg, InstantiateMsg, LockInfo, QueryMsg, ReceiveMsg};
-use crate::state::{Lock, State, LOCKS, STATE};
+use crate::msg::{
+    AllLocksGlobalResponse, AllLocksResponse, AllOwnersResponse, ContributorInfo, ExecuteMsg,
+    FeesResponse, GlobalLockInfo, Goal, InheritanceInfo, InstantiateMsg, LockInfo, LockResponse,
+    Memo, PauseResponse, QueryMsg, ReceiveMsg, SimulateLockResponse, SimulateUnlockResponse,
+    SudoMsg, TrancheConfig, TrancheInfo,
+};
+use crate::state::{
+    Contribution, Inheritance, Lock, Pause, State, Tranche, CLAIM_TOKENS, FEES, LOCKS, LOCK_SEQ,
+    PAUSE, PENDING_TRANCHE, STATE, TRANCHES, TRANCHE_CONFIG,
+};
 
 use cw2::set_contract_version;
-use cw20::{Balance, Cw20Coin, Cw20CoinVerified, Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw20::MinterResponse;
+use cw20::{Balance, Cw20Coin, Cw20CoinVerified, Cw20ExecuteMsg, Cw20ReceiveMsg, Denom};
+use cw_storage_plus::{Bound, PrefixBound};
+use cw_utils::parse_reply_instantiate_data;
+use sha2::{Digest, Sha256};
+use std::convert::TryFrom;
 
 // version info for migration info
 const CONTRACT_NAME: &str = "crates.io:cw-lockbox";
 const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
+// max memo size in bytes
+const MAX_MEMO_LENGTH: usize = 256;
+
+// max lock id size in bytes
+const MAX_LOCK_ID_LENGTH: usize = 64;
+
+// max deposit fee, 10%
+const MAX_DEPOSIT_FEE: u64 = 10;
+
+const TRANCHE_TOKEN_REPLY_ID: u64 = 1;
+const RELEASE_REPLY_ID: u64 = 2;
+
+// max third parties adding funds to a lock
+const MAX_CONTRIBUTORS: usize = 20;
+
+// settings for pagination
+const DEFAULT_LIMIT: u32 = 10;
+const MAX_LIMIT: u32 = 30;
+
 // Note, you can use StdResult in some functions where you do not
 // make use of the custom errors
 #[entry_point]
@@ -26,12 +59,20 @@ pub fn instantiate(
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
+    let deposit_fee = msg.deposit_fee.unwrap_or_default();
+    validate_deposit_fee(deposit_fee)?;
+
     let state = State {
         max_lock_time: msg.max_lock_time,
         owner: info.sender,
+        deposit_fee,
     };
     STATE.save(deps.storage, &state)?;
 
+    if let Some(config) = msg.tranche_config {
+        TRANCHE_CONFIG.save(deps.storage, &config)?;
+    }
+
     Ok(Response::default())
 }
 
@@ -43,19 +84,80 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    validate_funds(&info.funds)?;
+
     match msg {
-        ExecuteMsg::Lock { id, expire } => try_lock(
+        ExecuteMsg::Lock { id, expire, memo } => try_lock(
+            deps,
+            env,
+            Balance::from(info.funds),
+            &info.sender,
+            id,
+            expire,
+            memo,
+        ),
+        ExecuteMsg::IncreaseLock { id } => try_increase_lock(
+            deps,
+            env,
+            Balance::from(info.funds),
+            &info.sender,
+            &info.sender,
+            id,
+        ),
+        ExecuteMsg::Unlock { id, reveal } => try_unlock(deps, env, info, id, reveal),
+        ExecuteMsg::LockInheritance {
+            id,
+            heir,
+            inactivity_period,
+        } => try_lock_inheritance(
+            deps,
+            env,
+            Balance::from(info.funds),
+            &info.sender,
+            id,
+            heir,
+            inactivity_period,
+        ),
+        ExecuteMsg::Heartbeat { id } => try_heartbeat(deps, env, info, id),
+        ExecuteMsg::SetHeir { id, heir } => try_set_heir(deps, info, id, heir),
+        ExecuteMsg::ClaimInheritance { owner, id } => {
+            try_claim_inheritance(deps, env, info, owner, id)
+        }
+        ExecuteMsg::LockGoal { id, expire, goal } => try_lock_goal(
             deps,
             env,
             Balance::from(info.funds),
             &info.sender,
             id,
             expire,
+            goal,
         ),
-        ExecuteMsg::IncreaseLock { id } => {
-            try_increase_lock(deps, env, Balance::from(info.funds), &info.sender, id)
+        ExecuteMsg::IncreaseLockFor { owner, id } => {
+            let owner = deps.api.addr_validate(&owner)?;
+            try_increase_lock(
+                deps,
+                env,
+                Balance::from(info.funds),
+                &info.sender,
+                &owner,
+                id,
+            )
+        }
+        ExecuteMsg::CancelLock { id } => try_cancel_lock(deps, info, id),
+        ExecuteMsg::AllowContributors { id, allow } => {
+            try_allow_contributors(deps, info, id, allow)
+        }
+        ExecuteMsg::SetPause {
+            locks,
+            increases,
+            tokens,
+        } => try_set_pause(deps, info, locks, increases, tokens),
+        ExecuteMsg::UpdateDepositFee { fee } => try_update_deposit_fee(deps, info, fee),
+        ExecuteMsg::WithdrawFees { treasury } => try_withdraw_fees(deps, info, treasury),
+        ExecuteMsg::DepositTranche { expire } => {
+            try_deposit_tranche(deps, env, Balance::from(info.funds), &info.sender, expire)
         }
-        ExecuteMsg::Unlock { id } => try_unlock(deps, env, info, id),
+        ExecuteMsg::UpdateTrancheConfig { config } => try_update_tranche_config(deps, info, config),
         ExecuteMsg::Receive(msg) => try_receive(deps, env, info, msg),
     }
 }
@@ -65,437 +167,3119 @@ pub fn try_lock(
     env: Env,
     balance: Balance,
     sender: &Addr,
-    id: String,
+    id: Option<String>,
     expire: Timestamp,
+    memo: Option<Memo>,
 ) -> Result<Response, ContractError> {
     if balance.is_empty() {
         return Err(ContractError::EmptyBalance {});
     }
+    assert_locks_enabled(deps.storage, &balance)?;
 
-    let current_time = env.block.time;
-    if current_time.ge(&expire) {
-        return Err(ContractError::LowExpired {});
+    if let Some(memo) = &memo {
+        validate_memo(memo)?;
     }
 
     let state = STATE.load(deps.storage)?;
-    let diff = expire.minus_seconds(current_time.seconds());
-    if diff.seconds().ge(&state.max_lock_time) {
-        return Err(ContractError::HighExpired {});
-    }
+    validate_expire(&state, env.block.time, expire)?;
 
+    let (balance, fee) = take_deposit_fee(deps.storage, balance)?;
     let lock = Lock {
         create: env.block.time,
         expire,
-        funds: balance.into(),
+        funds: GenericBalance::try_from(balance)?,
+        fee,
+        memo,
+        inheritance: None,
+        goal: None,
+        allow_contributors: false,
+        contributions: vec![],
     };
-    let key = (sender, id.to_owned());
-
-    // try to store it, fail if the id was already in use
-    LOCKS.update(deps.storage, key, |existing| match existing {
-        None => Ok(lock),
-        Some(_) => Err(ContractError::AlreadyInUse {}),
-    })?;
+    let id = save_new_lock(deps, sender, id, lock)?;
 
     let res = Response::new()
         .add_attribute("action", "lock")
         .add_attribute("from", sender)
-        .add_attribute("id", id);
+        .add_attribute("id", id.clone())
+        .set_data(to_binary(&LockResponse { id })?);
     Ok(res)
 }
 
-pub fn try_increase_lock(
+pub fn try_lock_inheritance(
     deps: DepsMut,
     env: Env,
     balance: Balance,
     sender: &Addr,
-    id: String,
+    id: Option<String>,
+    heir: String,
+    inactivity_period: u64,
 ) -> Result<Response, ContractError> {
     if balance.is_empty() {
         return Err(ContractError::EmptyBalance {});
     }
+    assert_locks_enabled(deps.storage, &balance)?;
+
+    let heir = validate_heir(deps.as_ref(), sender, &heir)?;
+
+    // the lock expires when no heartbeat is received in the inactivity period
+    let expire = env.block.time.plus_seconds(inactivity_period);
+    let state = STATE.load(deps.storage)?;
+    validate_expire(&state, env.block.time, expire)?;
+
+    let (balance, fee) = take_deposit_fee(deps.storage, balance)?;
+    let lock = Lock {
+        create: env.block.time,
+        expire,
+        funds: GenericBalance::try_from(balance)?,
+        fee,
+        memo: None,
+        inheritance: Some(Inheritance {
+            heir: heir.clone(),
+            inactivity_period,
+        }),
+        goal: None,
+        allow_contributors: false,
+        contributions: vec![],
+    };
+    let id = save_new_lock(deps, sender, id, lock)?;
+
+    let res = Response::new()
+        .add_attribute("action", "lock_inheritance")
+        .add_attribute("from", sender)
+        .add_attribute("id", id.clone())
+        .add_attribute("heir", heir)
+        .set_data(to_binary(&LockResponse { id })?);
+    Ok(res)
+}
 
-    let key = (sender, id.to_owned());
+pub fn try_heartbeat(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: String,
+) -> Result<Response, ContractError> {
+    let key = (&info.sender, id.to_owned());
     let mut lock = LOCKS.load(deps.storage, key.clone())?;
 
+    let inheritance = lock
+        .inheritance
+        .as_ref()
+        .ok_or(ContractError::NotInheritanceLock {})?;
+
+    // too late, the heir can claim the funds
     if env.block.time.gt(&lock.expire) {
         return Err(ContractError::LockExpired {});
     }
 
-    lock.funds.add_tokens(balance);
+    lock.expire = env.block.time.plus_seconds(inheritance.inactivity_period);
     LOCKS.save(deps.storage, key, &lock)?;
 
     let res = Response::new()
-        .add_attribute("action", "increase_lock")
-        .add_attribute("from", sender)
-        .add_attribute("id", id);
+        .add_attribute("action", "heartbeat")
+        .add_attribute("from", info.sender)
+        .add_attribute("id", id)
+        .add_attribute("deadline", lock.expire.to_string());
     Ok(res)
 }
 
-pub fn try_unlock(
+pub fn try_set_heir(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: String,
+    heir: String,
+) -> Result<Response, ContractError> {
+    let heir = validate_heir(deps.as_ref(), &info.sender, &heir)?;
+
+    let key = (&info.sender, id.to_owned());
+    let mut lock = LOCKS.load(deps.storage, key.clone())?;
+
+    let inheritance = lock
+        .inheritance
+        .as_mut()
+        .ok_or(ContractError::NotInheritanceLock {})?;
+    inheritance.heir = heir.clone();
+    LOCKS.save(deps.storage, key, &lock)?;
+
+    let res = Response::new()
+        .add_attribute("action", "set_heir")
+        .add_attribute("from", info.sender)
+        .add_attribute("id", id)
+        .add_attribute("heir", heir);
+    Ok(res)
+}
+
+pub fn try_claim_inheritance(
     deps: DepsMut,
     env: Env,
     info: MessageInfo,
+    owner: String,
     id: String,
 ) -> Result<Response, ContractError> {
-    let key = (&info.sender, id);
+    let owner = deps.api.addr_validate(&owner)?;
+    let key = (&owner, id.to_owned());
     let lock = LOCKS.load(deps.storage, key.clone())?;
 
+    let inheritance = lock
+        .inheritance
+        .as_ref()
+        .ok_or(ContractError::NotInheritanceLock {})?;
+    if inheritance.heir.ne(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    // owner is still alive
     if env.block.time.le(&lock.expire) {
         return Err(ContractError::LockNotExpired {});
     }
 
-    // unlock all tokens
-    let messages = send_tokens(&info.sender, &lock.funds)?;
+    // transfer all tokens to the heir
+    let messages = release_tokens(&info.sender, &lock.funds)?;
 
-    // remove lock
     LOCKS.remove(deps.storage, key);
 
     let res = Response::new()
-        .add_attribute("action", "unlock")
+        .add_attribute("action", "claim_inheritance")
         .add_attribute("from", info.sender)
+        .add_attribute("owner", owner)
+        .add_attribute("id", id)
         .add_submessages(messages);
     Ok(res)
 }
 
-pub fn try_receive(
+pub fn try_lock_goal(
     deps: DepsMut,
     env: Env,
-    info: MessageInfo,
-    wrapper: Cw20ReceiveMsg,
+    balance: Balance,
+    sender: &Addr,
+    id: Option<String>,
+    expire: Timestamp,
+    goal: Goal,
 ) -> Result<Response, ContractError> {
-    let msg: ReceiveMsg = from_binary(&wrapper.msg)?;
-    let balance = Balance::Cw20(Cw20CoinVerified {
-        address: info.sender,
-        amount: wrapper.amount,
-    });
-    let api = deps.api;
-    let sender = &api.addr_validate(&wrapper.sender)?;
-    match msg {
-        ReceiveMsg::Lock { id, expire } => try_lock(deps, env, balance, sender, id, expire),
-        ReceiveMsg::IncreaseLock { id } => try_increase_lock(deps, env, balance, sender, id),
+    if balance.is_empty() {
+        return Err(ContractError::EmptyBalance {});
     }
-}
+    assert_locks_enabled(deps.storage, &balance)?;
 
-fn send_tokens(to: &Addr, balance: &GenericBalance) -> StdResult<Vec<SubMsg>> {
-    let native_balance = &balance.native;
-    let mut msgs: Vec<SubMsg> = if native_balance.is_empty() {
-        vec![]
-    } else {
-        vec![SubMsg::new(BankMsg::Send {
-            to_address: to.into(),
-            amount: native_balance.to_vec(),
-        })]
+    if goal.amount.is_zero() || goal.denom.is_empty() {
+        return Err(ContractError::InvalidGoal {});
+    }
+    if let Denom::Cw20(address) = &goal.denom {
+        deps.api.addr_validate(address.as_str())?;
+    }
+
+    let state = STATE.load(deps.storage)?;
+    validate_expire(&state, env.block.time, expire)?;
+
+    let (balance, fee) = take_deposit_fee(deps.storage, balance)?;
+    let lock = Lock {
+        create: env.block.time,
+        expire,
+        funds: GenericBalance::try_from(balance)?,
+        fee,
+        memo: None,
+        inheritance: None,
+        goal: Some(goal),
+        allow_contributors: false,
+        contributions: vec![],
     };
+    let id = save_new_lock(deps, sender, id, lock)?;
 
-    let cw20_balance = &balance.cw20;
-    let cw20_msgs: StdResult<Vec<_>> = cw20_balance
-        .iter()
-        .map(|c| {
-            let msg = Cw20ExecuteMsg::Transfer {
-                recipient: to.into(),
-                amount: c.amount,
-            };
-            let exec = WasmMsg::Execute {
-                contract_addr: c.address.to_string(),
-                msg: to_binary(&msg)?,
-                funds: vec![],
-            };
-            Ok(SubMsg::new(exec))
-        })
-        .collect();
-    msgs.append(&mut cw20_msgs?);
-    Ok(msgs)
+    let res = Response::new()
+        .add_attribute("action", "lock_goal")
+        .add_attribute("from", sender)
+        .add_attribute("id", id.clone())
+        .set_data(to_binary(&LockResponse { id })?);
+    Ok(res)
 }
 
-#[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::Lock { address, id } => to_binary(&query_lock(deps, address, id)?),
-        QueryMsg::AllLocks { address } => to_binary(&query_locks(deps, address)?),
+pub fn try_increase_lock(
+    deps: DepsMut,
+    env: Env,
+    balance: Balance,
+    sender: &Addr,
+    owner: &Addr,
+    id: String,
+) -> Result<Response, ContractError> {
+    if balance.is_empty() {
+        return Err(ContractError::EmptyBalance {});
     }
-}
+    assert_increases_enabled(deps.storage, &balance)?;
 
-fn query_lock(deps: Deps, address: String, id: String) -> StdResult<LockInfo> {
-    let key = (&deps.api.addr_validate(&address)?, id.to_owned());
-    let lock = LOCKS.load(deps.storage, key)?;
+    let key = (owner, id.to_owned());
+    let mut lock = LOCKS.load(deps.storage, key.clone())?;
+
+    if env.block.time.gt(&lock.expire) {
+        return Err(ContractError::LockExpired {});
+    }
+
+    let (balance, fee) = take_deposit_fee(deps.storage, balance)?;
+    lock.fee.merge(fee)?;
+
+    // track third-party funds, refundable if a goal lock is cancelled
+    if sender.ne(owner) {
+        if !lock.allow_contributors {
+            return Err(ContractError::ContributorsNotAllowed {});
+        }
+        validate_contribution(&lock, &balance)?;
+
+        match lock
+            .contributions
+            .iter_mut()
+            .find(|c| c.contributor.eq(sender))
+        {
+            Some(contribution) => contribution.funds.add_tokens(balance.clone())?,
+            None => {
+                validate_new_contributor(&lock, &balance)?;
+                lock.contributions.push(Contribution {
+                    contributor: sender.clone(),
+                    funds: GenericBalance::try_from(balance.clone())?,
+                })
+            }
+        }
+    }
+
+    lock.funds.add_tokens(balance)?;
+    LOCKS.save(deps.storage, key, &lock)?;
 
-    to_lock_info(lock, id)
+    let mut res = Response::new()
+        .add_attribute("action", "increase_lock")
+        .add_attribute("from", sender)
+        .add_attribute("id", id);
+    if sender.ne(owner) {
+        res = res.add_attribute("owner", owner);
+    }
+    Ok(res)
 }
 
-fn query_locks(deps: Deps, address: String) -> StdResult<AllLocksResponse> {
-    let owner_addr = &deps.api.addr_validate(&address)?;
+pub fn try_allow_contributors(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: String,
+    allow: bool,
+) -> Result<Response, ContractError> {
+    let key = (&info.sender, id.to_owned());
+    let mut lock = LOCKS.load(deps.storage, key.clone())?;
 
-    let locks_id: Result<Vec<_>, _> = LOCKS
-        .prefix(owner_addr)
-        .keys(deps.storage, None, None, Order::Ascending)
-        .collect();
+    lock.allow_contributors = allow;
+    LOCKS.save(deps.storage, key, &lock)?;
 
-    Ok(AllLocksResponse { locks: locks_id? })
+    let res = Response::new()
+        .add_attribute("action", "allow_contributors")
+        .add_attribute("from", info.sender)
+        .add_attribute("id", id)
+        .add_attribute("allow", allow.to_string());
+    Ok(res)
 }
 
-fn to_lock_info(lock: Lock, id: String) -> StdResult<LockInfo> {
-    // transform tokens
-    let native_balance = lock.funds.native;
-    let cw20_balance: StdResult<Vec<_>> = lock
-        .funds
-        .cw20
-        .into_iter()
-        .map(|token| {
-            Ok(Cw20Coin {
-                address: token.address.into(),
-                amount: token.amount,
-            })
-        })
-        .collect();
+pub fn try_cancel_lock(
+    deps: DepsMut,
+    info: MessageInfo,
+    id: String,
+) -> Result<Response, ContractError> {
+    let key = (&info.sender, id.to_owned());
+    let lock = LOCKS.load(deps.storage, key.clone())?;
 
-    let lock_info = LockInfo {
-        id,
-        create: lock.create,
-        expire: lock.expire,
-        native_balance,
-        cw20_balance: cw20_balance?,
-    };
+    if lock.goal.is_none() {
+        return Err(ContractError::NotGoalLock {});
+    }
+    if goal_reached(&lock) {
+        return Err(ContractError::GoalReached {});
+    }
 
-    Ok(lock_info)
+    // refund every contributor, the rest belongs to the owner
+    let mut funds = lock.funds;
+    let mut messages = vec![];
+    for contribution in lock.contributions.iter() {
+        funds.sub_tokens(&contribution.funds)?;
+        messages.append(&mut release_tokens(
+            &contribution.contributor,
+            &contribution.funds,
+        )?);
+    }
+    messages.append(&mut release_tokens(&info.sender, &funds)?);
+
+    LOCKS.remove(deps.storage, key);
+
+    let res = Response::new()
+        .add_attribute("action", "cancel_lock")
+        .add_attribute("from", info.sender)
+        .add_attribute("id", id)
+        .add_submessages(messages);
+    Ok(res)
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::testing::{
-        mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info,
+pub fn try_unlock(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: String,
+    reveal: Option<String>,
+) -> Result<Response, ContractError> {
+    let key = (&info.sender, id);
+    let lock = LOCKS.load(deps.storage, key.clone())?;
+
+    assert_unlockable(&lock, env.block.time)?;
+
+    let memo = match lock.memo {
+        Some(Memo::Commitment(hash)) => {
+            let plaintext = reveal.ok_or(ContractError::InvalidReveal {})?;
+            if Sha256::digest(plaintext.as_bytes()).as_slice() != hash.as_slice() {
+                return Err(ContractError::InvalidReveal {});
+            }
+            Some(plaintext)
+        }
+        Some(Memo::Text(text)) => Some(text),
+        None => None,
     };
-    use cosmwasm_std::{coins, from_binary, CosmosMsg, StdError, StdResult, SubMsg};
 
-    #[test]
-    fn proper_initialization() {
-        let mut deps = mock_dependencies();
+    // unlock all tokens
+    let messages = release_tokens(&info.sender, &lock.funds)?;
 
-        let msg = InstantiateMsg {
-            max_lock_time: 3600,
-        };
-        let info = mock_info("creator", &coins(1000, "earth"));
+    // remove lock
+    LOCKS.remove(deps.storage, key);
 
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(0, res.messages.len());
+    let mut res = Response::new()
+        .add_attribute("action", "unlock")
+        .add_attribute("from", info.sender);
+    if let Some(memo) = memo {
+        res = res.add_attribute("memo", memo);
+    }
+    Ok(res.add_submessages(messages))
+}
+
+pub fn try_set_pause(
+    deps: DepsMut,
+    info: MessageInfo,
+    locks: bool,
+    increases: bool,
+    tokens: Vec<String>,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.owner.ne(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let pause = Pause {
+        locks,
+        increases,
+        tokens,
+    };
+    PAUSE.save(deps.storage, &pause)?;
+
+    let res = Response::new()
+        .add_attribute("action", "set_pause")
+        .add_attribute("locks", locks.to_string())
+        .add_attribute("increases", increases.to_string())
+        .add_attribute("tokens", pause.tokens.join(","));
+    Ok(res)
+}
+
+pub fn try_update_deposit_fee(
+    deps: DepsMut,
+    info: MessageInfo,
+    fee: Decimal,
+) -> Result<Response, ContractError> {
+    validate_deposit_fee(fee)?;
+
+    STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
+        if state.owner.ne(&info.sender) {
+            return Err(ContractError::Unauthorized {});
+        }
+
+        state.deposit_fee = fee;
+        Ok(state)
+    })?;
+
+    let res = Response::new()
+        .add_attribute("action", "update_deposit_fee")
+        .add_attribute("fee", fee.to_string());
+    Ok(res)
+}
+
+pub fn try_withdraw_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    treasury: String,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.owner.ne(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let treasury = deps.api.addr_validate(&treasury)?;
+    let fees = FEES.may_load(deps.storage)?.unwrap_or_default();
+    if fees.native.is_empty() && fees.cw20.is_empty() {
+        return Err(ContractError::EmptyBalance {});
+    }
+
+    let messages = send_tokens(&treasury, &fees)?;
+    FEES.remove(deps.storage);
+
+    let res = Response::new()
+        .add_attribute("action", "withdraw_fees")
+        .add_attribute("treasury", treasury)
+        .add_submessages(messages);
+    Ok(res)
+}
+
+pub fn try_deposit_tranche(
+    deps: DepsMut,
+    env: Env,
+    balance: Balance,
+    sender: &Addr,
+    expire: Timestamp,
+) -> Result<Response, ContractError> {
+    if balance.is_empty() {
+        return Err(ContractError::EmptyBalance {});
+    }
+    assert_locks_enabled(deps.storage, &balance)?;
+
+    let config = TRANCHE_CONFIG
+        .may_load(deps.storage)?
+        .ok_or(ContractError::TranchesDisabled {})?;
+    let state = STATE.load(deps.storage)?;
+    validate_expire(&state, env.block.time, expire)?;
+    let expire = tranche_expire(expire, config.period);
+
+    let (balance, _) = take_deposit_fee(deps.storage, balance)?;
+    let (underlying, amount) = match balance {
+        Balance::Native(coins) if coins.0.len() == 1 => {
+            (Denom::Native(coins.0[0].denom.clone()), coins.0[0].amount)
+        }
+        Balance::Native(_) => return Err(ContractError::InvalidTrancheFunds {}),
+        Balance::Cw20(token) => (Denom::Cw20(token.address), token.amount),
+    };
+
+    let key = (denom_key(&underlying), expire.seconds());
+    let mut tranche = TRANCHES
+        .may_load(deps.storage, (&key.0, key.1))?
+        .unwrap_or(Tranche {
+            underlying,
+            expire,
+            claim_token: None,
+            supply: Uint128::zero(),
+        });
+    tranche.supply = tranche.supply.checked_add(amount).map_err(StdError::from)?;
+    TRANCHES.save(deps.storage, (&key.0, key.1), &tranche)?;
+
+    let message = match &tranche.claim_token {
+        Some(claim_token) => SubMsg::new(WasmMsg::Execute {
+            contract_addr: claim_token.to_string(),
+            msg: to_binary(&Cw20ExecuteMsg::Mint {
+                recipient: sender.to_string(),
+                amount,
+            })?,
+            funds: vec![],
+        }),
+        None => {
+            // the first deposit is minted on the claim token instantiation
+            let msg = cw20_base::msg::InstantiateMsg {
+                name: format!("Lockbox tranche {}", expire.seconds()),
+                symbol: "LOCKED".to_string(),
+                decimals: 6,
+                initial_balances: vec![Cw20Coin {
+                    address: sender.to_string(),
+                    amount,
+                }],
+                mint: Some(MinterResponse {
+                    minter: env.contract.address.to_string(),
+                    cap: None,
+                }),
+                marketing: None,
+            };
+            PENDING_TRANCHE.save(deps.storage, &key)?;
+
+            SubMsg::reply_on_success(
+                WasmMsg::Instantiate {
+                    admin: None,
+                    code_id: config.cw20_code_id,
+                    msg: to_binary(&msg)?,
+                    funds: vec![],
+                    label: format!("lockbox tranche {} {}", key.0, key.1),
+                },
+                TRANCHE_TOKEN_REPLY_ID,
+            )
+        }
+    };
+
+    let res = Response::new()
+        .add_attribute("action", "deposit_tranche")
+        .add_attribute("from", sender)
+        .add_attribute("token", key.0)
+        .add_attribute("expire", expire.to_string())
+        .add_attribute("amount", amount)
+        .add_submessage(message);
+    Ok(res)
+}
+
+pub fn try_redeem(
+    deps: DepsMut,
+    env: Env,
+    claim_token: Addr,
+    sender: &Addr,
+    amount: Uint128,
+) -> Result<Response, ContractError> {
+    let (token, expire) = CLAIM_TOKENS
+        .may_load(deps.storage, &claim_token)?
+        .ok_or(ContractError::InvalidClaimToken {})?;
+    let mut tranche = TRANCHES.load(deps.storage, (&token, expire))?;
+
+    if env.block.time.le(&tranche.expire) {
+        return Err(ContractError::LockNotExpired {});
+    }
+
+    tranche.supply = tranche.supply.checked_sub(amount).map_err(StdError::from)?;
+    TRANCHES.save(deps.storage, (&token, expire), &tranche)?;
+
+    // burn the claim tokens and release the underlying 1:1
+    let burn = SubMsg::new(WasmMsg::Execute {
+        contract_addr: claim_token.to_string(),
+        msg: to_binary(&Cw20ExecuteMsg::Burn { amount })?,
+        funds: vec![],
+    });
+    let underlying = match tranche.underlying {
+        Denom::Native(denom) => GenericBalance {
+            native: vec![coin(amount.u128(), denom)],
+            cw20: vec![],
+        },
+        Denom::Cw20(address) => GenericBalance {
+            native: vec![],
+            cw20: vec![Cw20CoinVerified { address, amount }],
+        },
+    };
+    let messages = send_tokens(sender, &underlying)?;
+
+    let res = Response::new()
+        .add_attribute("action", "redeem")
+        .add_attribute("from", sender)
+        .add_attribute("token", token)
+        .add_attribute("amount", amount)
+        .add_submessage(burn)
+        .add_submessages(messages);
+    Ok(res)
+}
+
+pub fn try_update_tranche_config(
+    deps: DepsMut,
+    info: MessageInfo,
+    config: TrancheConfig,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+    if state.owner.ne(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    TRANCHE_CONFIG.save(deps.storage, &config)?;
+
+    let res = Response::new()
+        .add_attribute("action", "update_tranche_config")
+        .add_attribute("cw20_code_id", config.cw20_code_id.to_string())
+        .add_attribute("period", config.period.to_string());
+    Ok(res)
+}
+
+pub fn try_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let msg: ReceiveMsg = from_binary(&wrapper.msg)?;
+    let balance = Balance::Cw20(Cw20CoinVerified {
+        address: info.sender.clone(),
+        amount: wrapper.amount,
+    });
+    let api = deps.api;
+    let sender = &api.addr_validate(&wrapper.sender)?;
+
+    match msg {
+        ReceiveMsg::Lock { id, expire, memo } => {
+            try_lock(deps, env, balance, sender, id, expire, memo)
+        }
+        ReceiveMsg::IncreaseLock { id } => {
+            try_increase_lock(deps, env, balance, sender, sender, id)
+        }
+        ReceiveMsg::LockInheritance {
+            id,
+            heir,
+            inactivity_period,
+        } => try_lock_inheritance(deps, env, balance, sender, id, heir, inactivity_period),
+        ReceiveMsg::LockGoal { id, expire, goal } => {
+            try_lock_goal(deps, env, balance, sender, id, expire, goal)
+        }
+        ReceiveMsg::IncreaseLockFor { owner, id } => {
+            let owner = api.addr_validate(&owner)?;
+            try_increase_lock(deps, env, balance, sender, &owner, id)
+        }
+        ReceiveMsg::DepositTranche { expire } => {
+            try_deposit_tranche(deps, env, balance, sender, expire)
+        }
+        ReceiveMsg::Redeem {} => try_redeem(deps, env, info.sender, sender, wrapper.amount),
+    }
+}
+
+/// Rounds up the expire timestamp to the end of its tranche
+fn tranche_expire(expire: Timestamp, period: u64) -> Timestamp {
+    let period = period.max(1);
+    let seconds = expire.seconds().div_ceil(period) * period;
+    Timestamp::from_seconds(seconds)
+}
+
+fn denom_key(denom: &Denom) -> String {
+    match denom {
+        Denom::Native(denom) => denom.clone(),
+        Denom::Cw20(address) => address.to_string(),
+    }
+}
+
+fn validate_deposit_fee(fee: Decimal) -> Result<(), ContractError> {
+    let max = Decimal::percent(MAX_DEPOSIT_FEE);
+    if fee.gt(&max) {
+        return Err(ContractError::InvalidFee { max });
+    }
+
+    Ok(())
+}
+
+/// Splits the deposit fee from the balance, the fee is added to the fee ledger
+fn take_deposit_fee(
+    storage: &mut dyn Storage,
+    balance: Balance,
+) -> Result<(Balance, GenericBalance), ContractError> {
+    let state = STATE.load(storage)?;
+    let mut fee = GenericBalance::default();
+    if state.deposit_fee.is_zero() {
+        return Ok((balance, fee));
+    }
+
+    let net = match balance {
+        Balance::Native(mut coins) => {
+            for token in coins.0.iter_mut() {
+                let amount = token.amount * state.deposit_fee;
+                if !amount.is_zero() {
+                    token.amount -= amount;
+                    fee.add_tokens(Balance::from(vec![coin(amount.u128(), &token.denom)]))?;
+                }
+            }
+            Balance::Native(coins)
+        }
+        Balance::Cw20(mut token) => {
+            let amount = token.amount * state.deposit_fee;
+            if !amount.is_zero() {
+                token.amount -= amount;
+                fee.add_tokens(Balance::Cw20(Cw20CoinVerified {
+                    address: token.address.clone(),
+                    amount,
+                }))?;
+            }
+            Balance::Cw20(token)
+        }
+    };
+
+    let mut fees = FEES.may_load(storage)?.unwrap_or_default();
+    fees.merge(fee.clone())?;
+    FEES.save(storage, &fees)?;
+
+    Ok((net, fee))
+}
+
+fn assert_locks_enabled(storage: &dyn Storage, balance: &Balance) -> Result<(), ContractError> {
+    let pause = PAUSE.may_load(storage)?.unwrap_or_default();
+    if pause.locks {
+        return Err(ContractError::Paused {});
+    }
+
+    assert_tokens_enabled(&pause, balance)
+}
+
+fn assert_increases_enabled(storage: &dyn Storage, balance: &Balance) -> Result<(), ContractError> {
+    let pause = PAUSE.may_load(storage)?.unwrap_or_default();
+    if pause.increases {
+        return Err(ContractError::IncreasesPaused {});
+    }
+
+    assert_tokens_enabled(&pause, balance)
+}
+
+fn assert_tokens_enabled(pause: &Pause, balance: &Balance) -> Result<(), ContractError> {
+    let paused = match balance {
+        Balance::Native(coins) => coins
+            .0
+            .iter()
+            .find(|c| pause.tokens.contains(&c.denom))
+            .map(|c| c.denom.clone()),
+        Balance::Cw20(token) => {
+            Some(token.address.to_string()).filter(|address| pause.tokens.contains(address))
+        }
+    };
+
+    match paused {
+        Some(token) => Err(ContractError::TokenPaused { token }),
+        None => Ok(()),
+    }
+}
+
+fn assert_unlockable(lock: &Lock, time: Timestamp) -> Result<(), ContractError> {
+    // the owner of an inheritance lock can unlock at any time
+    if lock.inheritance.is_none() && !goal_reached(lock) && time.le(&lock.expire) {
+        return Err(ContractError::LockNotExpired {});
+    }
+
+    Ok(())
+}
+
+/// Third parties can add native coins, the goal token or a cw20 token the lock already holds
+fn validate_contribution(lock: &Lock, balance: &Balance) -> Result<(), ContractError> {
+    if let Balance::Cw20(token) = balance {
+        let goal_token = matches!(
+            &lock.goal,
+            Some(Goal { denom: Denom::Cw20(address), .. }) if *address == token.address
+        );
+        let held = lock.funds.cw20.iter().any(|c| c.address == token.address);
+        if !goal_token && !held {
+            return Err(ContractError::InvalidContribution {});
+        }
+    }
+
+    Ok(())
+}
+
+/// Contributors are capped, new ones of a goal lock send a minimum share of the goal
+fn validate_new_contributor(lock: &Lock, balance: &Balance) -> Result<(), ContractError> {
+    if lock.contributions.len() >= MAX_CONTRIBUTORS {
+        return Err(ContractError::TooManyContributors {
+            max: MAX_CONTRIBUTORS,
+        });
+    }
+    if let Some(goal) = &lock.goal {
+        let min = goal.amount.multiply_ratio(1u128, MAX_CONTRIBUTORS as u128);
+        let amount = GenericBalance::try_from(balance.clone())?.amount_of(&goal.denom);
+        if amount < min {
+            return Err(ContractError::ContributionTooLow { min });
+        }
+    }
+
+    Ok(())
+}
+
+fn goal_reached(lock: &Lock) -> bool {
+    match &lock.goal {
+        Some(goal) => lock.funds.amount_of(&goal.denom).ge(&goal.amount),
+        None => false,
+    }
+}
+
+fn validate_funds(funds: &[Coin]) -> Result<(), ContractError> {
+    for (i, token) in funds.iter().enumerate() {
+        if token.amount.is_zero() || funds[..i].iter().any(|c| c.denom == token.denom) {
+            return Err(ContractError::InvalidFunds {});
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_expire(
+    state: &State,
+    current_time: Timestamp,
+    expire: Timestamp,
+) -> Result<(), ContractError> {
+    if current_time.ge(&expire) {
+        return Err(ContractError::LowExpired {});
+    }
+
+    let diff = expire.minus_seconds(current_time.seconds());
+    if diff.seconds().ge(&state.max_lock_time) {
+        return Err(ContractError::HighExpired {});
+    }
+
+    Ok(())
+}
+
+fn validate_heir(deps: Deps, owner: &Addr, heir: &str) -> Result<Addr, ContractError> {
+    let heir = deps.api.addr_validate(heir)?;
+    if heir.eq(owner) {
+        return Err(ContractError::InvalidHeir {});
+    }
+
+    Ok(heir)
+}
+
+/// Stores a new lock, generating a sequential id if none is given
+fn save_new_lock(
+    deps: DepsMut,
+    owner: &Addr,
+    id: Option<String>,
+    lock: Lock,
+) -> Result<String, ContractError> {
+    let id = match id {
+        Some(id) => {
+            validate_lock_id(&id)?;
+            id
+        }
+        None => next_lock_id(deps.storage, owner)?,
+    };
+    let key = (owner, id.to_owned());
+
+    // try to store it, fail if the id was already in use
+    LOCKS.update(deps.storage, key, |existing| match existing {
+        None => Ok(lock),
+        Some(_) => Err(ContractError::AlreadyInUse {}),
+    })?;
+
+    Ok(id)
+}
+
+fn next_lock_id(storage: &mut dyn Storage, owner: &Addr) -> StdResult<String> {
+    let mut seq = LOCK_SEQ.may_load(storage, owner)?.unwrap_or_default();
+
+    // skip ids already chosen by the owner
+    let id = loop {
+        seq += 1;
+        let id = seq.to_string();
+        if !LOCKS.has(storage, (owner, id.to_owned())) {
+            break id;
+        }
+    };
+    LOCK_SEQ.save(storage, owner, &seq)?;
+
+    Ok(id)
+}
+
+fn validate_lock_id(id: &str) -> Result<(), ContractError> {
+    let valid_char = |c: char| c.is_ascii_alphanumeric() || c == '-' || c == '_' || c == '.';
+    if id.is_empty() || id.len() > MAX_LOCK_ID_LENGTH || !id.chars().all(valid_char) {
+        return Err(ContractError::InvalidLockId {
+            max: MAX_LOCK_ID_LENGTH,
+        });
+    }
+
+    Ok(())
+}
+
+fn validate_memo(memo: &Memo) -> Result<(), ContractError> {
+    match memo {
+        Memo::Text(text) if text.len() > MAX_MEMO_LENGTH => Err(ContractError::MemoTooLong {
+            max: MAX_MEMO_LENGTH,
+        }),
+        Memo::Commitment(hash) if hash.len() != 32 => Err(ContractError::InvalidCommitment {}),
+        _ => Ok(()),
+    }
+}
+
+fn send_tokens(to: &Addr, balance: &GenericBalance) -> StdResult<Vec<SubMsg>> {
+    let native_balance = &balance.native;
+    let mut msgs: Vec<SubMsg> = if native_balance.is_empty() {
+        vec![]
+    } else {
+        vec![SubMsg::new(BankMsg::Send {
+            to_address: to.into(),
+            amount: native_balance.to_vec(),
+        })]
+    };
+
+    let cw20_balance = &balance.cw20;
+    let cw20_msgs: StdResult<Vec<_>> = cw20_balance
+        .iter()
+        .map(|c| {
+            let msg = Cw20ExecuteMsg::Transfer {
+                recipient: to.into(),
+                amount: c.amount,
+            };
+            let exec = WasmMsg::Execute {
+                contract_addr: c.address.to_string(),
+                msg: to_binary(&msg)?,
+                funds: vec![],
+            };
+            Ok(SubMsg::new(exec))
+        })
+        .collect();
+    msgs.append(&mut cw20_msgs?);
+    Ok(msgs)
+}
+
+/// Lock payouts, a failing cw20 transfer does not revert the other transfers
+fn release_tokens(to: &Addr, balance: &GenericBalance) -> StdResult<Vec<SubMsg>> {
+    let msgs = send_tokens(to, balance)?
+        .into_iter()
+        .map(|sub_msg| match sub_msg.msg {
+            CosmosMsg::Wasm(msg) => SubMsg::reply_on_error(msg, RELEASE_REPLY_ID),
+            _ => sub_msg,
+        })
+        .collect();
+    Ok(msgs)
+}
+
+#[entry_point]
+pub fn reply(deps: DepsMut, _env: Env, msg: Reply) -> Result<Response, ContractError> {
+    match msg.id {
+        TRANCHE_TOKEN_REPLY_ID => reply_tranche_token(deps, msg),
+        RELEASE_REPLY_ID => reply_release(msg),
+        id => Err(ContractError::UnknownReplyId { id }),
+    }
+}
+
+fn reply_tranche_token(deps: DepsMut, msg: Reply) -> Result<Response, ContractError> {
+    let res = parse_reply_instantiate_data(msg)?;
+    let claim_token = deps.api.addr_validate(&res.contract_address)?;
+
+    let (token, expire) = PENDING_TRANCHE.load(deps.storage)?;
+    PENDING_TRANCHE.remove(deps.storage);
+
+    let mut tranche = TRANCHES.load(deps.storage, (&token, expire))?;
+    tranche.claim_token = Some(claim_token.clone());
+    TRANCHES.save(deps.storage, (&token, expire), &tranche)?;
+    CLAIM_TOKENS.save(deps.storage, &claim_token, &(token, expire))?;
+
+    let res = Response::new()
+        .add_attribute("action", "create_claim_token")
+        .add_attribute("claim_token", claim_token);
+    Ok(res)
+}
+
+/// The failed transfer is reverted, its tokens stay in the contract
+fn reply_release(msg: Reply) -> Result<Response, ContractError> {
+    let error = match msg.result {
+        SubMsgResult::Err(error) => error,
+        SubMsgResult::Ok(_) => String::new(),
+    };
+
+    let res = Response::new()
+        .add_attribute("action", "release_failed")
+        .add_attribute("error", error);
+    Ok(res)
+}
+
+#[entry_point]
+pub fn sudo(deps: DepsMut, _env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::ForceUnlock { owner, id } => sudo_force_unlock(deps, owner, id),
+        SudoMsg::PauseLocks { paused } => sudo_pause_locks(deps, paused),
+        SudoMsg::UpdateMaxLockTime { max_lock_time } => {
+            sudo_update_max_lock_time(deps, max_lock_time)
+        }
+    }
+}
+
+pub fn sudo_force_unlock(
+    deps: DepsMut,
+    owner: String,
+    id: String,
+) -> Result<Response, ContractError> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let key = (&owner, id.to_owned());
+    let lock = LOCKS.load(deps.storage, key.clone())?;
+
+    // return all tokens to the owner, ignoring lock conditions
+    let messages = release_tokens(&owner, &lock.funds)?;
+
+    LOCKS.remove(deps.storage, key);
+
+    // indexers tell governance unlocks apart from owner unlocks
+    let event = Event::new("force_unlock")
+        .add_attribute("owner", owner.clone())
+        .add_attribute("id", id.clone());
+    let res = Response::new()
+        .add_attribute("action", "sudo_force_unlock")
+        .add_attribute("owner", owner)
+        .add_attribute("id", id)
+        .add_event(event)
+        .add_submessages(messages);
+    Ok(res)
+}
+
+pub fn sudo_pause_locks(deps: DepsMut, paused: bool) -> Result<Response, ContractError> {
+    let mut pause = PAUSE.may_load(deps.storage)?.unwrap_or_default();
+    pause.locks = paused;
+    PAUSE.save(deps.storage, &pause)?;
+
+    let res = Response::new()
+        .add_attribute("action", "sudo_pause_locks")
+        .add_attribute("paused", paused.to_string());
+    Ok(res)
+}
+
+pub fn sudo_update_max_lock_time(
+    deps: DepsMut,
+    max_lock_time: u64,
+) -> Result<Response, ContractError> {
+    if max_lock_time == 0 {
+        return Err(ContractError::InvalidMaxLockTime {});
+    }
+    STATE.update(deps.storage, |mut state| -> StdResult<_> {
+        state.max_lock_time = max_lock_time;
+        Ok(state)
+    })?;
+
+    let res = Response::new()
+        .add_attribute("action", "sudo_update_max_lock_time")
+        .add_attribute("max_lock_time", max_lock_time.to_string());
+    Ok(res)
+}
+
+#[entry_point]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::Lock { address, id } => to_binary(&query_lock(deps, env, address, id)?),
+        QueryMsg::AllLocks { address } => to_binary(&query_locks(deps, address)?),
+        QueryMsg::AllOwners { start_after, limit } => {
+            to_binary(&query_all_owners(deps, start_after, limit)?)
+        }
+        QueryMsg::AllLocksGlobal { start_after, limit } => {
+            to_binary(&query_all_locks_global(deps, start_after, limit)?)
+        }
+        QueryMsg::PauseStatus {} => to_binary(&query_pause(deps)?),
+        QueryMsg::Fees {} => to_binary(&query_fees(deps)?),
+        QueryMsg::Tranche { token, expire } => to_binary(&query_tranche(deps, token, expire)?),
+        QueryMsg::ClaimToken { address } => to_binary(&query_claim_token(deps, address)?),
+        QueryMsg::SimulateUnlock { owner, id, at_time } => {
+            let at_time = at_time.unwrap_or(env.block.time);
+            to_binary(&query_simulate_unlock(deps, owner, id, at_time)?)
+        }
+        QueryMsg::SimulateLock {
+            owner,
+            id,
+            expire,
+            at_time,
+        } => {
+            let at_time = at_time.unwrap_or(env.block.time);
+            to_binary(&query_simulate_lock(deps, owner, id, expire, at_time)?)
+        }
+    }
+}
+
+fn query_lock(deps: Deps, env: Env, address: String, id: String) -> StdResult<LockInfo> {
+    let key = (&deps.api.addr_validate(&address)?, id.to_owned());
+    let lock = LOCKS.load(deps.storage, key)?;
+
+    to_lock_info(lock, id, &env)
+}
+
+fn query_locks(deps: Deps, address: String) -> StdResult<AllLocksResponse> {
+    let owner_addr = &deps.api.addr_validate(&address)?;
+
+    let locks_id: Result<Vec<_>, _> = LOCKS
+        .prefix(owner_addr)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .collect();
+
+    Ok(AllLocksResponse { locks: locks_id? })
+}
+
+fn query_all_owners(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AllOwnersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let mut start = start_after
+        .map(|s| deps.api.addr_validate(&s))
+        .transpose()?;
+
+    // jump to the next owner instead of walking through all its locks
+    let mut owners = vec![];
+    while owners.len() < limit {
+        let next = LOCKS
+            .prefix_range(
+                deps.storage,
+                start.as_ref().map(PrefixBound::exclusive),
+                None,
+                Order::Ascending,
+            )
+            .next()
+            .transpose()?;
+        match next {
+            Some(((owner, _), _)) => {
+                owners.push(owner.clone());
+                start = Some(owner);
+            }
+            None => break,
+        }
+    }
+
+    Ok(AllOwnersResponse { owners })
+}
+
+fn query_all_locks_global(
+    deps: Deps,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<AllLocksGlobalResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after
+        .map(|(owner, id)| deps.api.addr_validate(&owner).map(|owner| (owner, id)))
+        .transpose()?;
+    let start = start
+        .as_ref()
+        .map(|(owner, id)| Bound::exclusive((owner, id.to_owned())));
+
+    let locks: StdResult<Vec<_>> = LOCKS
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| {
+            item.map(|((owner, id), lock)| GlobalLockInfo {
+                owner,
+                id,
+                expire: lock.expire,
+                native_balance: lock.funds.native,
+                cw20_balance: to_cw20_coins(lock.funds.cw20),
+            })
+        })
+        .collect();
+
+    Ok(AllLocksGlobalResponse { locks: locks? })
+}
+
+fn query_pause(deps: Deps) -> StdResult<PauseResponse> {
+    let pause = PAUSE.may_load(deps.storage)?.unwrap_or_default();
+
+    Ok(PauseResponse {
+        locks: pause.locks,
+        increases: pause.increases,
+        tokens: pause.tokens,
+    })
+}
+
+fn query_fees(deps: Deps) -> StdResult<FeesResponse> {
+    let state = STATE.load(deps.storage)?;
+    let fees = FEES.may_load(deps.storage)?.unwrap_or_default();
+
+    Ok(FeesResponse {
+        deposit_fee: state.deposit_fee,
+        native_balance: fees.native,
+        cw20_balance: to_cw20_coins(fees.cw20),
+    })
+}
+
+fn query_tranche(deps: Deps, token: String, expire: Timestamp) -> StdResult<TrancheInfo> {
+    let config = TRANCHE_CONFIG.load(deps.storage)?;
+    let expire = tranche_expire(expire, config.period);
+    let tranche = TRANCHES.load(deps.storage, (&token, expire.seconds()))?;
+
+    Ok(to_tranche_info(tranche))
+}
+
+fn query_claim_token(deps: Deps, address: String) -> StdResult<TrancheInfo> {
+    let claim_token = deps.api.addr_validate(&address)?;
+    let (token, expire) = CLAIM_TOKENS.load(deps.storage, &claim_token)?;
+    let tranche = TRANCHES.load(deps.storage, (&token, expire))?;
+
+    Ok(to_tranche_info(tranche))
+}
+
+fn query_simulate_unlock(
+    deps: Deps,
+    owner: String,
+    id: String,
+    at_time: Timestamp,
+) -> StdResult<SimulateUnlockResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let lock = LOCKS.load(deps.storage, (&owner, id))?;
+
+    let lock_not_expired = matches!(
+        assert_unlockable(&lock, at_time),
+        Err(ContractError::LockNotExpired {})
+    );
+    let messages = send_tokens(&owner, &lock.funds)?
+        .into_iter()
+        .map(|sub_msg| sub_msg.msg)
+        .collect();
+
+    Ok(SimulateUnlockResponse {
+        lock_not_expired,
+        reveal_required: matches!(lock.memo, Some(Memo::Commitment(_))),
+        messages,
+    })
+}
+
+fn query_simulate_lock(
+    deps: Deps,
+    owner: String,
+    id: Option<String>,
+    expire: Timestamp,
+    at_time: Timestamp,
+) -> StdResult<SimulateLockResponse> {
+    let owner = deps.api.addr_validate(&owner)?;
+    let state = STATE.load(deps.storage)?;
+    let pause = PAUSE.may_load(deps.storage)?.unwrap_or_default();
+
+    let result = if pause.locks {
+        Err(ContractError::Paused {})
+    } else {
+        validate_expire(&state, at_time, expire).and_then(|_| match id {
+            Some(id) if LOCKS.has(deps.storage, (&owner, id.to_owned())) => {
+                Err(ContractError::AlreadyInUse {})
+            }
+            Some(id) => validate_lock_id(&id),
+            None => Ok(()),
+        })
+    };
+
+    Ok(SimulateLockResponse {
+        error: result.err().map(|err| err.to_string()),
+    })
+}
+
+fn to_tranche_info(tranche: Tranche) -> TrancheInfo {
+    TrancheInfo {
+        underlying: tranche.underlying,
+        expire: tranche.expire,
+        claim_token: tranche.claim_token,
+        supply: tranche.supply,
+    }
+}
+
+fn to_lock_info(lock: Lock, id: String, env: &Env) -> StdResult<LockInfo> {
+    // memo is a time capsule, hidden until expire
+    let memo = if env.block.time.gt(&lock.expire) {
+        lock.memo
+    } else {
+        None
+    };
+
+    let heartbeat_deadline = lock.expire;
+    let inheritance = lock.inheritance.map(|inheritance| InheritanceInfo {
+        heir: inheritance.heir,
+        inactivity_period: inheritance.inactivity_period,
+        heartbeat_deadline,
+    });
+
+    let contributors = lock
+        .contributions
+        .into_iter()
+        .map(|c| ContributorInfo {
+            address: c.contributor,
+            native_balance: c.funds.native,
+            cw20_balance: to_cw20_coins(c.funds.cw20),
+        })
+        .collect();
+
+    // transform tokens
+    let native_balance = lock.funds.native;
+    let cw20_balance = to_cw20_coins(lock.funds.cw20);
+
+    let lock_info = LockInfo {
+        id,
+        create: lock.create,
+        expire: lock.expire,
+        native_balance,
+        cw20_balance,
+        memo,
+        inheritance,
+        goal: lock.goal,
+        allow_contributors: lock.allow_contributors,
+        contributors,
+        fee_native_balance: lock.fee.native,
+        fee_cw20_balance: to_cw20_coins(lock.fee.cw20),
+    };
+
+    Ok(lock_info)
+}
+
+fn to_cw20_coins(tokens: Vec<Cw20CoinVerified>) -> Vec<Cw20Coin> {
+    tokens
+        .into_iter()
+        .map(|token| Cw20Coin {
+            address: token.address.into(),
+            amount: token.amount,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info, MOCK_CONTRACT_ADDR,
+    };
+    use cosmwasm_std::{
+        coins, from_binary, CosmosMsg, HexBinary, StdError, SubMsg, SubMsgResponse, SubMsgResult,
+    };
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
+        };
+        let info = mock_info("creator", &coins(1000, "earth"));
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+    }
+
+    #[test]
+    fn lock() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // empty funds
+        let info = mock_info("anyone", &[]);
+        let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(10),
+            memo: None,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info, msg);
+        match res {
+            Err(ContractError::EmptyBalance {}) => {}
+            _ => panic!("Must return EmptyBalance error"),
+        }
+
+        // zero or duplicate coins
+        for funds in [
+            vec![coin(0, "token")],
+            vec![coin(2, "token"), coin(1, "token")],
+        ] {
+            let info = mock_info("anyone", &funds);
+            let msg = ExecuteMsg::Lock {
+                id: Some("1".into()),
+                expire: Timestamp::from_seconds(10),
+                memo: None,
+            };
+            let res = execute(deps.as_mut(), mock_env(), info, msg);
+            match res {
+                Err(ContractError::InvalidFunds {}) => {}
+                _ => panic!("Must return InvalidFunds error"),
+            }
+        }
+
+        // lower expire
+        let info = mock_info("anyone", &coins(2, "token"));
+        let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(10),
+            memo: None,
+        };
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(100);
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        match res {
+            Err(ContractError::LowExpired {}) => {}
+            _ => panic!("Must return LowExpired error"),
+        }
+
+        // high expire
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info("anyone", &coins(2, "token"));
+        let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(4000),
+            memo: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        match res {
+            Err(ContractError::HighExpired {}) => {}
+            _ => panic!("Must return HighExpired error"),
+        }
+
+        // lock funds 1
+        let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(200),
+            memo: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // should exists lock
+        let msg = QueryMsg::Lock {
+            address: "anyone".into(),
+            id: "1".into(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: LockInfo = from_binary(&res).unwrap();
+        assert_eq!(0, value.create.seconds());
+        assert_eq!(200, value.expire.seconds());
+
+        // try lock same id
+        let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(200),
+            memo: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        match res {
+            Err(ContractError::AlreadyInUse {}) => {}
+            _ => panic!("Must return AlreadyInUse error"),
+        }
+
+        // lock funds 2
+        let msg = ExecuteMsg::Lock {
+            id: Some("2".into()),
+            expire: Timestamp::from_seconds(300),
+            memo: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // should exists lock
+        let msg = QueryMsg::Lock {
+            address: "anyone".into(),
+            id: "2".into(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: LockInfo = from_binary(&res).unwrap();
+        assert_eq!(300, value.expire.seconds());
+
+        let res = query(
+            deps.as_ref(),
+            mock_env(),
+            QueryMsg::AllLocks {
+                address: "anyone".into(),
+            },
+        )
+        .unwrap();
+        let value: AllLocksResponse = from_binary(&res).unwrap();
+        assert_eq!(2, value.locks.len())
+    }
+
+    #[test]
+    fn increase_lock() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // lock funds
+        let mut env = mock_env();
+        let info = mock_info("anyone", &coins(2, "token"));
+        env.block.time = Timestamp::from_seconds(100);
+        let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(200),
+            memo: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // try increase lock invalid id
+        let info = mock_info("anyone", &coins(5, "token"));
+        let msg = ExecuteMsg::IncreaseLock { id: "2".into() };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        match res {
+            Err(ContractError::Std(StdError::NotFound { .. })) => {}
+            _ => panic!("Must return StdError::NotFound error"),
+        }
+
+        // try increase lock after expire
+        let info = mock_info("anyone", &coins(5, "token"));
+        let msg = ExecuteMsg::IncreaseLock { id: "1".into() };
+        env.block.time = Timestamp::from_seconds(201);
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        match res {
+            Err(ContractError::LockExpired {}) => {}
+            _ => panic!("Must return LockExpired error"),
+        }
+
+        // increase valid lock
+        let msg = ExecuteMsg::IncreaseLock { id: "1".into() };
+        env.block.time = Timestamp::from_seconds(120);
+        let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // query funds lock
+        let msg = QueryMsg::Lock {
+            address: "anyone".into(),
+            id: "1".into(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: LockInfo = from_binary(&res).unwrap();
+        assert_eq!(coins(7, "token"), value.native_balance);
+    }
+
+    #[test]
+    fn unlock() {
+        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // lock funds
+        let info = mock_info("anyone", &coins(2, "token"));
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(400),
+            memo: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+
+        // cannot unlock until expire
+        let auth_info = mock_info("anyone", &[]);
+        let msg = ExecuteMsg::Unlock {
+            id: "1".into(),
+            reveal: None,
+        };
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(100);
+        let res = execute(deps.as_mut(), env.clone(), auth_info, msg);
+        match res {
+            Err(ContractError::LockNotExpired {}) => {}
+            _ => panic!("Must return LockNotExpired error"),
+        }
+
+        // unlock funds
+        let auth_info = mock_info("anyone", &[]);
+        let msg = ExecuteMsg::Unlock {
+            id: "1".into(),
+            reveal: None,
+        };
+        env.block.time = Timestamp::from_seconds(401);
+        let res = execute(deps.as_mut(), env, auth_info, msg).unwrap();
+        assert_eq!(1, res.messages.len());
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "anyone".into(),
+                amount: coins(2, "token")
+            }))
+        );
+
+        // should lock completed
+        let msg = QueryMsg::Lock {
+            address: "anyone".into(),
+            id: "1".into(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg);
+
+        match res {
+            Err(StdError::NotFound { .. }) => {}
+            _ => panic!("Must return StdError::NotFound error"),
+        }
+    }
+
+    #[test]
+    fn time_capsule_memo() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("anyone", &coins(2, "token"));
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+
+        // memo too long
+        let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(100),
+            memo: Some(Memo::Text("a".repeat(MAX_MEMO_LENGTH + 1))),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        match res {
+            Err(ContractError::MemoTooLong { .. }) => {}
+            _ => panic!("Must return MemoTooLong error"),
+        }
+
+        // invalid commitment
+        let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(100),
+            memo: Some(Memo::Commitment(HexBinary::from(vec![1u8; 8]))),
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        match res {
+            Err(ContractError::InvalidCommitment {}) => {}
+            _ => panic!("Must return InvalidCommitment error"),
+        }
+
+        // lock with text memo
+        let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(100),
+            memo: Some(Memo::Text("hello future".into())),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // lock with commitment
+        let hash = Sha256::digest(b"secret").to_vec();
+        let msg = ExecuteMsg::Lock {
+            id: Some("2".into()),
+            expire: Timestamp::from_seconds(100),
+            memo: Some(Memo::Commitment(hash.clone().into())),
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // memo hidden before expire
+        let msg = QueryMsg::Lock {
+            address: "anyone".into(),
+            id: "1".into(),
+        };
+        let res = query(deps.as_ref(), env.clone(), msg.clone()).unwrap();
+        let value: LockInfo = from_binary(&res).unwrap();
+        assert_eq!(None, value.memo);
+
+        // memo visible after expire
+        env.block.time = Timestamp::from_seconds(101);
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let value: LockInfo = from_binary(&res).unwrap();
+        assert_eq!(Some(Memo::Text("hello future".into())), value.memo);
+
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &[]),
+            ExecuteMsg::Unlock {
+                id: "1".into(),
+                reveal: None,
+            },
+        )
+        .unwrap();
+        assert_eq!(res.attributes[2], ("memo", "hello future"));
+
+        // commitment requires the plaintext
+        let auth_info = mock_info("anyone", &[]);
+        let msg = ExecuteMsg::Unlock {
+            id: "2".into(),
+            reveal: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), auth_info.clone(), msg);
+        match res {
+            Err(ContractError::InvalidReveal {}) => {}
+            _ => panic!("Must return InvalidReveal error"),
+        }
+
+        let msg = ExecuteMsg::Unlock {
+            id: "2".into(),
+            reveal: Some("other".into()),
+        };
+        let res = execute(deps.as_mut(), env.clone(), auth_info.clone(), msg);
+        match res {
+            Err(ContractError::InvalidReveal {}) => {}
+            _ => panic!("Must return InvalidReveal error"),
+        }
+
+        let msg = ExecuteMsg::Unlock {
+            id: "2".into(),
+            reveal: Some("secret".into()),
+        };
+        let res = execute(deps.as_mut(), env, auth_info, msg).unwrap();
+        assert_eq!(1, res.messages.len());
+        assert_eq!(res.attributes[2], ("memo", "secret"));
+    }
+
+    #[test]
+    fn inheritance_lock() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("owner", &coins(2, "token"));
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+
+        // heir cannot be the owner
+        let msg = ExecuteMsg::LockInheritance {
+            id: Some("1".into()),
+            heir: "owner".into(),
+            inactivity_period: 100,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        match res {
+            Err(ContractError::InvalidHeir {}) => {}
+            _ => panic!("Must return InvalidHeir error"),
+        }
+
+        // inactivity period over max lock time
+        let msg = ExecuteMsg::LockInheritance {
+            id: Some("1".into()),
+            heir: "heir".into(),
+            inactivity_period: 4000,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        match res {
+            Err(ContractError::HighExpired {}) => {}
+            _ => panic!("Must return HighExpired error"),
+        }
+
+        let msg = ExecuteMsg::LockInheritance {
+            id: Some("1".into()),
+            heir: "heir".into(),
+            inactivity_period: 100,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // heartbeat moves the deadline
+        let owner_info = mock_info("owner", &[]);
+        env.block.time = Timestamp::from_seconds(50);
+        let msg = ExecuteMsg::Heartbeat { id: "1".into() };
+        let _res = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+
+        let msg = QueryMsg::Lock {
+            address: "owner".into(),
+            id: "1".into(),
+        };
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let value: LockInfo = from_binary(&res).unwrap();
+        assert_eq!(
+            Some(InheritanceInfo {
+                heir: Addr::unchecked("heir"),
+                inactivity_period: 100,
+                heartbeat_deadline: Timestamp::from_seconds(150),
+            }),
+            value.inheritance
+        );
+
+        // only the owner can change the heir
+        let msg = ExecuteMsg::SetHeir {
+            id: "1".into(),
+            heir: "new_heir".into(),
+        };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("heir", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::Std(StdError::NotFound { .. })) => {}
+            _ => panic!("Must return StdError::NotFound error"),
+        }
+        let _res = execute(deps.as_mut(), env.clone(), owner_info, msg).unwrap();
+
+        // heir cannot claim before the deadline
+        let msg = ExecuteMsg::ClaimInheritance {
+            owner: "owner".into(),
+            id: "1".into(),
+        };
+        let heir_info = mock_info("new_heir", &[]);
+        let res = execute(deps.as_mut(), env.clone(), heir_info.clone(), msg.clone());
+        match res {
+            Err(ContractError::LockNotExpired {}) => {}
+            _ => panic!("Must return LockNotExpired error"),
+        }
+
+        // old heir is not authorized
+        env.block.time = Timestamp::from_seconds(151);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("heir", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return Unauthorized error"),
+        }
+
+        // owner missed the heartbeat
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::Heartbeat { id: "1".into() },
+        );
+        match res {
+            Err(ContractError::LockExpired {}) => {}
+            _ => panic!("Must return LockExpired error"),
+        }
+
+        let res = execute(deps.as_mut(), env, heir_info, msg).unwrap();
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "new_heir".into(),
+                amount: coins(2, "token")
+            }))
+        );
+    }
+
+    #[test]
+    fn unlock_inheritance_any_time() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let info = mock_info("owner", &coins(2, "token"));
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = ExecuteMsg::LockInheritance {
+            id: Some("1".into()),
+            heir: "heir".into(),
+            inactivity_period: 100,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // heartbeat is only for inheritance locks
+        let info = mock_info("owner", &coins(2, "token"));
+        let msg = ExecuteMsg::Lock {
+            id: Some("2".into()),
+            expire: Timestamp::from_seconds(100),
+            memo: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("owner", &[]),
+            ExecuteMsg::Heartbeat { id: "2".into() },
+        );
+        match res {
+            Err(ContractError::NotInheritanceLock {}) => {}
+            _ => panic!("Must return NotInheritanceLock error"),
+        }
+
+        // owner cancels before the deadline
+        env.block.time = Timestamp::from_seconds(10);
+        let msg = ExecuteMsg::Unlock {
+            id: "1".into(),
+            reveal: None,
+        };
+        let res = execute(deps.as_mut(), env, mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "owner".into(),
+                amount: coins(2, "token")
+            }))
+        );
     }
 
     #[test]
-    fn lock() {
+    fn goal_lock() {
         let mut deps = mock_dependencies();
 
         let msg = InstantiateMsg {
             max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
         };
         let info = mock_info("creator", &[]);
         let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        // empty funds
-        let info = mock_info("anyone", &[]);
-        let msg = ExecuteMsg::Lock {
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+
+        // invalid goal
+        let info = mock_info("owner", &coins(2, "token"));
+        let msg = ExecuteMsg::LockGoal {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(1000),
+            goal: Goal {
+                denom: Denom::Native("token".into()),
+                amount: Uint128::zero(),
+            },
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        match res {
+            Err(ContractError::InvalidGoal {}) => {}
+            _ => panic!("Must return InvalidGoal error"),
+        }
+
+        let msg = ExecuteMsg::LockGoal {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(1000),
+            goal: Goal {
+                denom: Denom::Cw20(Addr::unchecked("cw20")),
+                amount: Uint128::new(100),
+            },
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // contributors must be allowed by the owner
+        let top_up = ExecuteMsg::IncreaseLockFor {
+            owner: "owner".into(),
             id: "1".into(),
-            expire: Timestamp::from_seconds(10),
         };
-        let res = execute(deps.as_mut(), mock_env(), info, msg);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("parent", &coins(3, "token")),
+            top_up.clone(),
+        );
         match res {
-            Err(ContractError::EmptyBalance {}) => {}
-            _ => panic!("Must return EmptyBalance error"),
+            Err(ContractError::ContributorsNotAllowed {}) => {}
+            _ => panic!("Must return ContributorsNotAllowed error"),
         }
+        let msg = ExecuteMsg::AllowContributors {
+            id: "1".into(),
+            allow: true,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
 
-        // lower expire
-        let info = mock_info("anyone", &coins(2, "token"));
+        // regular locks do not accept contributors
         let msg = ExecuteMsg::Lock {
+            id: Some("2".into()),
+            expire: Timestamp::from_seconds(1000),
+            memo: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let msg = ExecuteMsg::IncreaseLockFor {
+            owner: "owner".into(),
+            id: "2".into(),
+        };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("parent", &coins(3, "token")),
+            msg,
+        );
+        match res {
+            Err(ContractError::ContributorsNotAllowed {}) => {}
+            _ => panic!("Must return ContributorsNotAllowed error"),
+        }
+
+        // new contributors send at least 1/MAX_CONTRIBUTORS of the goal
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("parent", &coins(3, "token")),
+            top_up,
+        );
+        match res {
+            Err(ContractError::ContributionTooLow { min }) => assert_eq!(Uint128::new(5), min),
+            _ => panic!("Must return ContributionTooLow error"),
+        }
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "employer".into(),
+            amount: Uint128::new(50),
+            msg: to_binary(&ReceiveMsg::IncreaseLockFor {
+                owner: "owner".into(),
+                id: "1".into(),
+            })
+            .unwrap(),
+        });
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("fake-cw20", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::InvalidContribution {}) => {}
+            _ => panic!("Must return InvalidContribution error"),
+        }
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("cw20", &[]), msg).unwrap();
+
+        // goal not reached
+        let owner_info = mock_info("owner", &[]);
+        let msg = ExecuteMsg::Unlock {
             id: "1".into(),
-            expire: Timestamp::from_seconds(10),
+            reveal: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg);
+        match res {
+            Err(ContractError::LockNotExpired {}) => {}
+            _ => panic!("Must return LockNotExpired error"),
+        }
+
+        // cancel refunds contributors
+        let msg = ExecuteMsg::CancelLock { id: "1".into() };
+        let res = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg).unwrap();
+        assert_eq!(2, res.messages.len());
+        assert_eq!(
+            res.messages[0],
+            SubMsg::reply_on_error(
+                WasmMsg::Execute {
+                    contract_addr: "cw20".into(),
+                    msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                        recipient: "employer".into(),
+                        amount: Uint128::new(50),
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                },
+                RELEASE_REPLY_ID
+            )
+        );
+        assert_eq!(
+            res.messages[1],
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "owner".into(),
+                amount: coins(2, "token")
+            }))
+        );
+
+        // a failing cw20 refund does not revert the other refunds
+        let msg = Reply {
+            id: RELEASE_REPLY_ID,
+            result: SubMsgResult::Err("transfer failed".into()),
+        };
+        let res = reply(deps.as_mut(), env.clone(), msg).unwrap();
+        assert_eq!(res.attributes[1], ("error", "transfer failed"));
+
+        // only goal locks can be cancelled
+        let msg = ExecuteMsg::CancelLock { id: "2".into() };
+        let res = execute(deps.as_mut(), env, owner_info, msg);
+        match res {
+            Err(ContractError::NotGoalLock {}) => {}
+            _ => panic!("Must return NotGoalLock error"),
+        }
+    }
+
+    #[test]
+    fn goal_lock_reached() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
         };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
         let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(100);
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = ExecuteMsg::LockGoal {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(1000),
+            goal: Goal {
+                denom: Denom::Native("token".into()),
+                amount: Uint128::new(10),
+            },
+        };
+        let info = mock_info("owner", &coins(2, "token"));
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let msg = ExecuteMsg::AllowContributors {
+            id: "1".into(),
+            allow: true,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let msg = ExecuteMsg::IncreaseLockFor {
+            owner: "owner".into(),
+            id: "1".into(),
+        };
+        let info = mock_info("parent", &coins(8, "token"));
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        let msg = QueryMsg::Lock {
+            address: "owner".into(),
+            id: "1".into(),
+        };
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let value: LockInfo = from_binary(&res).unwrap();
+        assert_eq!(coins(10, "token"), value.native_balance);
+
+        // cannot cancel after the goal
+        let owner_info = mock_info("owner", &[]);
+        let msg = ExecuteMsg::CancelLock { id: "1".into() };
+        let res = execute(deps.as_mut(), env.clone(), owner_info.clone(), msg);
         match res {
-            Err(ContractError::LowExpired {}) => {}
-            _ => panic!("Must return LowExpired error"),
+            Err(ContractError::GoalReached {}) => {}
+            _ => panic!("Must return GoalReached error"),
         }
 
-        // high expire
+        // unlock before expire
+        env.block.time = Timestamp::from_seconds(10);
+        let msg = ExecuteMsg::Unlock {
+            id: "1".into(),
+            reveal: None,
+        };
+        let res = execute(deps.as_mut(), env, owner_info, msg).unwrap();
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "owner".into(),
+                amount: coins(10, "token")
+            }))
+        );
+    }
+
+    #[test]
+    fn increase_lock_for() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(1000),
+            memo: None,
+        };
+        let info = mock_info("child", &coins(2, "token"));
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+
+        // contributors denied by default
+        let top_up = ExecuteMsg::IncreaseLockFor {
+            owner: "child".into(),
+            id: "1".into(),
+        };
+        let parent_info = mock_info("parent", &coins(5, "token"));
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            parent_info.clone(),
+            top_up.clone(),
+        );
+        match res {
+            Err(ContractError::ContributorsNotAllowed {}) => {}
+            _ => panic!("Must return ContributorsNotAllowed error"),
+        }
+
+        // only the owner can allow contributors
+        let msg = ExecuteMsg::AllowContributors {
+            id: "1".into(),
+            allow: true,
+        };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("parent", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::Std(StdError::NotFound { .. })) => {}
+            _ => panic!("Must return StdError::NotFound error"),
+        }
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("child", &[]), msg).unwrap();
+
+        let _res = execute(deps.as_mut(), env.clone(), parent_info, top_up.clone()).unwrap();
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("parent", &coins(1, "token")),
+            top_up.clone(),
+        )
+        .unwrap();
+
+        // cw20 contributions only in tokens the lock holds
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "employer".into(),
+            amount: Uint128::new(50),
+            msg: to_binary(&ReceiveMsg::IncreaseLockFor {
+                owner: "child".into(),
+                id: "1".into(),
+            })
+            .unwrap(),
+        });
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("cw20", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::InvalidContribution {}) => {}
+            _ => panic!("Must return InvalidContribution error"),
+        }
+        let increase = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "child".into(),
+            amount: Uint128::new(10),
+            msg: to_binary(&ReceiveMsg::IncreaseLock { id: "1".into() }).unwrap(),
+        });
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("cw20", &[]), increase).unwrap();
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("cw20", &[]), msg).unwrap();
+
+        let msg = QueryMsg::Lock {
+            address: "child".into(),
+            id: "1".into(),
+        };
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let value: LockInfo = from_binary(&res).unwrap();
+        assert_eq!(coins(8, "token"), value.native_balance);
+        assert!(value.allow_contributors);
+        assert_eq!(
+            vec![
+                ContributorInfo {
+                    address: Addr::unchecked("parent"),
+                    native_balance: coins(6, "token"),
+                    cw20_balance: vec![],
+                },
+                ContributorInfo {
+                    address: Addr::unchecked("employer"),
+                    native_balance: vec![],
+                    cw20_balance: vec![Cw20Coin {
+                        address: "cw20".into(),
+                        amount: Uint128::new(50),
+                    }],
+                },
+            ],
+            value.contributors
+        );
+
+        // the number of contributors is capped
+        for i in 2..MAX_CONTRIBUTORS {
+            let info = mock_info(&format!("contributor-{}", i), &coins(1, "token"));
+            let _res = execute(deps.as_mut(), env.clone(), info, top_up.clone()).unwrap();
+        }
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("latecomer", &coins(1, "token")),
+            top_up.clone(),
+        );
+        match res {
+            Err(ContractError::TooManyContributors { max }) => assert_eq!(MAX_CONTRIBUTORS, max),
+            _ => panic!("Must return TooManyContributors error"),
+        }
+
+        // deny again
+        let msg = ExecuteMsg::AllowContributors {
+            id: "1".into(),
+            allow: false,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("child", &[]), msg).unwrap();
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("parent", &coins(1, "token")),
+            top_up,
+        );
+        match res {
+            Err(ContractError::ContributorsNotAllowed {}) => {}
+            _ => panic!("Must return ContributorsNotAllowed error"),
+        }
+    }
+
+    #[test]
+    fn sudo_governance() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
         let info = mock_info("anyone", &coins(2, "token"));
         let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(1000),
+            memo: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // force unlock before expire
+        let msg = SudoMsg::ForceUnlock {
+            owner: "anyone".into(),
             id: "1".into(),
-            expire: Timestamp::from_seconds(4000),
         };
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        let res = sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert_eq!(res.attributes[0], ("action", "sudo_force_unlock"));
+        assert_eq!(
+            res.events,
+            vec![Event::new("force_unlock")
+                .add_attribute("owner", "anyone")
+                .add_attribute("id", "1")]
+        );
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "anyone".into(),
+                amount: coins(2, "token")
+            }))
+        );
+        let msg = QueryMsg::Lock {
+            address: "anyone".into(),
+            id: "1".into(),
+        };
+        let res = query(deps.as_ref(), env.clone(), msg);
+        match res {
+            Err(StdError::NotFound { .. }) => {}
+            _ => panic!("Must return StdError::NotFound error"),
+        }
+
+        // pause new locks
+        let res = sudo(
+            deps.as_mut(),
+            env.clone(),
+            SudoMsg::PauseLocks { paused: true },
+        )
+        .unwrap();
+        assert_eq!(res.attributes[1], ("paused", "true"));
+        let msg = ExecuteMsg::Lock {
+            id: Some("2".into()),
+            expire: Timestamp::from_seconds(1000),
+            memo: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
+        match res {
+            Err(ContractError::Paused {}) => {}
+            _ => panic!("Must return Paused error"),
+        }
+
+        let _res = sudo(
+            deps.as_mut(),
+            env.clone(),
+            SudoMsg::PauseLocks { paused: false },
+        )
+        .unwrap();
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // update max lock time
+        let res = sudo(
+            deps.as_mut(),
+            env.clone(),
+            SudoMsg::UpdateMaxLockTime { max_lock_time: 0 },
+        );
+        match res {
+            Err(ContractError::InvalidMaxLockTime {}) => {}
+            _ => panic!("Must return InvalidMaxLockTime error"),
+        }
+        let msg = SudoMsg::UpdateMaxLockTime {
+            max_lock_time: 7200,
+        };
+        let res = sudo(deps.as_mut(), env.clone(), msg).unwrap();
+        assert_eq!(res.attributes[1], ("max_lock_time", "7200"));
+        let msg = ExecuteMsg::Lock {
+            id: Some("3".into()),
+            expire: Timestamp::from_seconds(5000),
+            memo: None,
+        };
+        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+    }
+
+    #[test]
+    fn pause() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info("anyone", &coins(2, "token"));
+        let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(100),
+            memo: None,
+        };
+        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+
+        // only owner
+        let msg = ExecuteMsg::SetPause {
+            locks: false,
+            increases: true,
+            tokens: vec!["token".into(), "bad-cw20".into()],
+        };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return Unauthorized error"),
+        }
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::PauseStatus {}).unwrap();
+        let value: PauseResponse = from_binary(&res).unwrap();
+        assert!(!value.locks);
+        assert!(value.increases);
+        assert_eq!(
+            vec!["token".to_string(), "bad-cw20".to_string()],
+            value.tokens
+        );
+
+        // increases paused
+        let msg = ExecuteMsg::IncreaseLock { id: "1".into() };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &coins(2, "other")),
+            msg,
+        );
+        match res {
+            Err(ContractError::IncreasesPaused {}) => {}
+            _ => panic!("Must return IncreasesPaused error"),
+        }
+
+        // paused native token
+        let msg = ExecuteMsg::Lock {
+            id: Some("2".into()),
+            expire: Timestamp::from_seconds(100),
+            memo: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info, msg.clone());
+        match res {
+            Err(ContractError::TokenPaused { token }) => assert_eq!("token", token),
+            _ => panic!("Must return TokenPaused error"),
+        }
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &coins(2, "other")),
+            msg,
+        )
+        .unwrap();
+
+        // paused cw20 token
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "anyone".into(),
+            amount: Uint128::new(50),
+            msg: to_binary(&ReceiveMsg::Lock {
+                id: Some("3".into()),
+                expire: Timestamp::from_seconds(100),
+                memo: None,
+            })
+            .unwrap(),
+        });
+        let res = execute(deps.as_mut(), env.clone(), mock_info("bad-cw20", &[]), msg);
+        match res {
+            Err(ContractError::TokenPaused { token }) => assert_eq!("bad-cw20", token),
+            _ => panic!("Must return TokenPaused error"),
+        }
+
+        // pause new locks
+        let msg = ExecuteMsg::SetPause {
+            locks: true,
+            increases: true,
+            tokens: vec![],
+        };
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg).unwrap();
+        let msg = ExecuteMsg::LockGoal {
+            id: Some("4".into()),
+            expire: Timestamp::from_seconds(100),
+            goal: Goal {
+                denom: Denom::Native("other".into()),
+                amount: Uint128::new(10),
+            },
+        };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &coins(2, "other")),
+            msg,
+        );
         match res {
-            Err(ContractError::HighExpired {}) => {}
-            _ => panic!("Must return HighExpired error"),
+            Err(ContractError::Paused {}) => {}
+            _ => panic!("Must return Paused error"),
         }
 
-        // lock funds 1
-        let msg = ExecuteMsg::Lock {
+        // unlock is always available
+        env.block.time = Timestamp::from_seconds(101);
+        let msg = ExecuteMsg::Unlock {
             id: "1".into(),
-            expire: Timestamp::from_seconds(200),
+            reveal: None,
         };
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let res = execute(deps.as_mut(), env, mock_info("anyone", &[]), msg).unwrap();
+        assert_eq!(1, res.messages.len());
+    }
 
-        // should exists lock
-        let msg = QueryMsg::Lock {
-            address: "anyone".into(),
-            id: "1".into(),
-        };
-        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let value: LockInfo = from_binary(&res).unwrap();
-        assert_eq!(0, value.create.seconds());
-        assert_eq!(200, value.expire.seconds());
+    #[test]
+    fn deposit_fee() {
+        let mut deps = mock_dependencies();
 
-        // try lock same id
-        let msg = ExecuteMsg::Lock {
-            id: "1".into(),
-            expire: Timestamp::from_seconds(200),
+        // fee is capped
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: Some(Decimal::percent(11)),
+            tranche_config: None,
         };
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        let info = mock_info("creator", &[]);
+        let res = instantiate(deps.as_mut(), mock_env(), info.clone(), msg);
         match res {
-            Err(ContractError::AlreadyInUse {}) => {}
-            _ => panic!("Must return AlreadyInUse error"),
+            Err(ContractError::InvalidFee { .. }) => {}
+            _ => panic!("Must return InvalidFee error"),
         }
 
-        // lock funds 2
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: Some(Decimal::percent(1)),
+            tranche_config: None,
+        };
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
         let msg = ExecuteMsg::Lock {
-            id: "2".into(),
-            expire: Timestamp::from_seconds(300),
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(100),
+            memo: None,
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let info = mock_info("anyone", &coins(100, "token"));
+        let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
 
-        // should exists lock
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "anyone".into(),
+            amount: Uint128::new(200),
+            msg: to_binary(&ReceiveMsg::IncreaseLock { id: "1".into() }).unwrap(),
+        });
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("cw20", &[]), msg).unwrap();
+
+        // net amount and fee paid
         let msg = QueryMsg::Lock {
             address: "anyone".into(),
-            id: "2".into(),
+            id: "1".into(),
         };
-        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
         let value: LockInfo = from_binary(&res).unwrap();
-        assert_eq!(300, value.expire.seconds());
+        assert_eq!(coins(99, "token"), value.native_balance);
+        assert_eq!(Uint128::new(198), value.cw20_balance[0].amount);
+        assert_eq!(coins(1, "token"), value.fee_native_balance);
+        assert_eq!(Uint128::new(2), value.fee_cw20_balance[0].amount);
+
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::Fees {}).unwrap();
+        let value: FeesResponse = from_binary(&res).unwrap();
+        assert_eq!(Decimal::percent(1), value.deposit_fee);
+        assert_eq!(coins(1, "token"), value.native_balance);
+        assert_eq!(
+            vec![Cw20Coin {
+                address: "cw20".into(),
+                amount: Uint128::new(2)
+            }],
+            value.cw20_balance
+        );
 
-        let res = query(
-            deps.as_ref(),
-            mock_env(),
-            QueryMsg::AllLocks {
-                address: "anyone".into(),
-            },
+        // only owner
+        let msg = ExecuteMsg::UpdateDepositFee {
+            fee: Decimal::percent(20),
+        };
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg);
+        match res {
+            Err(ContractError::InvalidFee { .. }) => {}
+            _ => panic!("Must return InvalidFee error"),
+        }
+        let msg = ExecuteMsg::WithdrawFees {
+            treasury: "treasury".into(),
+        };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return Unauthorized error"),
+        }
+
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            msg.clone(),
         )
         .unwrap();
-        let value: AllLocksResponse = from_binary(&res).unwrap();
-        assert_eq!(2, value.locks.len())
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "treasury".into(),
+                    amount: coins(1, "token")
+                }),
+                SubMsg::new(WasmMsg::Execute {
+                    contract_addr: "cw20".into(),
+                    msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                        recipient: "treasury".into(),
+                        amount: Uint128::new(2),
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                })
+            ]
+        );
+
+        // fee ledger is empty
+        let res = execute(deps.as_mut(), env.clone(), mock_info("creator", &[]), msg);
+        match res {
+            Err(ContractError::EmptyBalance {}) => {}
+            _ => panic!("Must return EmptyBalance error"),
+        }
+
+        // unlock the net amount
+        env.block.time = Timestamp::from_seconds(101);
+        let msg = ExecuteMsg::Unlock {
+            id: "1".into(),
+            reveal: None,
+        };
+        let res = execute(deps.as_mut(), env, mock_info("anyone", &[]), msg).unwrap();
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(BankMsg::Send {
+                to_address: "anyone".into(),
+                amount: coins(99, "token")
+            })
+        );
+    }
+
+    fn instantiate_reply(id: u64, contract_address: &str) -> Reply {
+        // protobuf encoded MsgInstantiateContractResponse
+        let mut data = vec![0x0a, contract_address.len() as u8];
+        data.extend_from_slice(contract_address.as_bytes());
+
+        Reply {
+            id,
+            result: SubMsgResult::Ok(SubMsgResponse {
+                events: vec![],
+                data: Some(data.into()),
+            }),
+        }
     }
 
     #[test]
-    fn increase_lock() {
+    fn tranches() {
         let mut deps = mock_dependencies();
 
         let msg = InstantiateMsg {
             max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
         };
         let info = mock_info("creator", &[]);
         let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        // lock funds
         let mut env = mock_env();
-        let info = mock_info("anyone", &coins(2, "token"));
-        env.block.time = Timestamp::from_seconds(100);
-        let msg = ExecuteMsg::Lock {
-            id: "1".into(),
-            expire: Timestamp::from_seconds(200),
+        env.block.time = Timestamp::from_seconds(0);
+
+        // tranches disabled
+        let msg = ExecuteMsg::DepositTranche {
+            expire: Timestamp::from_seconds(150),
         };
-        let _res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let info = mock_info("alice", &coins(100, "token"));
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone());
+        match res {
+            Err(ContractError::TranchesDisabled {}) => {}
+            _ => panic!("Must return TranchesDisabled error"),
+        }
 
-        // try increase lock invalid id
-        let info = mock_info("anyone", &coins(5, "token"));
-        let msg = ExecuteMsg::IncreaseLock { id: "2".into() };
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        let config = ExecuteMsg::UpdateTrancheConfig {
+            config: TrancheConfig {
+                cw20_code_id: 7,
+                period: 1000,
+            },
+        };
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("alice", &[]),
+            config.clone(),
+        );
         match res {
-            Err(ContractError::Std(StdError::NotFound { .. })) => {}
-            _ => panic!("Must return StdError::NotFound error"),
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return Unauthorized error"),
         }
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("creator", &[]),
+            config,
+        )
+        .unwrap();
 
-        // try increase lock after expire
-        let info = mock_info("anyone", &coins(5, "token"));
-        let msg = ExecuteMsg::IncreaseLock { id: "1".into() };
-        env.block.time = Timestamp::from_seconds(201);
-        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+        // single token only
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("alice", &[coin(1, "token"), coin(1, "other")]),
+            msg.clone(),
+        );
         match res {
-            Err(ContractError::LockExpired {}) => {}
-            _ => panic!("Must return LockExpired error"),
+            Err(ContractError::InvalidTrancheFunds {}) => {}
+            _ => panic!("Must return InvalidTrancheFunds error"),
         }
 
-        // increase valid lock
-        let msg = ExecuteMsg::IncreaseLock { id: "1".into() };
-        env.block.time = Timestamp::from_seconds(120);
+        // first deposit instantiates the claim token
         let res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        assert_eq!(1, res.messages.len());
+        assert_eq!(TRANCHE_TOKEN_REPLY_ID, res.messages[0].id);
+        match &res.messages[0].msg {
+            CosmosMsg::Wasm(WasmMsg::Instantiate { code_id, msg, .. }) => {
+                assert_eq!(7, *code_id);
+                let msg: cw20_base::msg::InstantiateMsg = from_binary(msg).unwrap();
+                assert_eq!(
+                    vec![Cw20Coin {
+                        address: "alice".into(),
+                        amount: Uint128::new(100)
+                    }],
+                    msg.initial_balances
+                );
+                assert_eq!(MOCK_CONTRACT_ADDR, msg.mint.unwrap().minter);
+            }
+            _ => panic!("Must instantiate the claim token"),
+        }
+        let _res = reply(
+            deps.as_mut(),
+            env.clone(),
+            instantiate_reply(TRANCHE_TOKEN_REPLY_ID, "claim-token"),
+        )
+        .unwrap();
 
-        // query funds lock
-        let msg = QueryMsg::Lock {
-            address: "anyone".into(),
-            id: "1".into(),
+        // deposits in the same bucket mint the claim token
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "bob".into(),
+            amount: Uint128::new(50),
+            msg: to_binary(&ReceiveMsg::DepositTranche {
+                expire: Timestamp::from_seconds(1000),
+            })
+            .unwrap(),
+        });
+        let _res = execute(deps.as_mut(), env.clone(), mock_info("cw20", &[]), msg).unwrap();
+        let msg = ExecuteMsg::DepositTranche {
+            expire: Timestamp::from_seconds(999),
         };
-        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let value: LockInfo = from_binary(&res).unwrap();
-        assert_eq!(coins(7, "token"), value.native_balance);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("bob", &coins(20, "token")),
+            msg,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "claim-token".into(),
+                msg: to_binary(&Cw20ExecuteMsg::Mint {
+                    recipient: "bob".into(),
+                    amount: Uint128::new(20),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+
+        let msg = QueryMsg::Tranche {
+            token: "token".into(),
+            expire: Timestamp::from_seconds(500),
+        };
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let value: TrancheInfo = from_binary(&res).unwrap();
+        assert_eq!(
+            TrancheInfo {
+                underlying: Denom::Native("token".into()),
+                expire: Timestamp::from_seconds(1000),
+                claim_token: Some(Addr::unchecked("claim-token")),
+                supply: Uint128::new(120),
+            },
+            value
+        );
+
+        // redeem after the tranche expire
+        let redeem = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "carol".into(),
+            amount: Uint128::new(30),
+            msg: to_binary(&ReceiveMsg::Redeem {}).unwrap(),
+        });
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("claim-token", &[]),
+            redeem.clone(),
+        );
+        match res {
+            Err(ContractError::LockNotExpired {}) => {}
+            _ => panic!("Must return LockNotExpired error"),
+        }
+        env.block.time = Timestamp::from_seconds(1001);
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("cw20", &[]),
+            redeem.clone(),
+        );
+        match res {
+            Err(ContractError::InvalidClaimToken {}) => {}
+            _ => panic!("Must return InvalidClaimToken error"),
+        }
+        let res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("claim-token", &[]),
+            redeem,
+        )
+        .unwrap();
+        assert_eq!(
+            res.messages,
+            vec![
+                SubMsg::new(WasmMsg::Execute {
+                    contract_addr: "claim-token".into(),
+                    msg: to_binary(&Cw20ExecuteMsg::Burn {
+                        amount: Uint128::new(30),
+                    })
+                    .unwrap(),
+                    funds: vec![],
+                }),
+                SubMsg::new(BankMsg::Send {
+                    to_address: "carol".into(),
+                    amount: coins(30, "token")
+                }),
+            ]
+        );
+
+        let msg = QueryMsg::ClaimToken {
+            address: "claim-token".into(),
+        };
+        let res = query(deps.as_ref(), env, msg).unwrap();
+        let value: TrancheInfo = from_binary(&res).unwrap();
+        assert_eq!(Uint128::new(90), value.supply);
     }
 
     #[test]
-    fn unlock() {
-        let mut deps = mock_dependencies_with_balance(&coins(2, "token"));
+    fn lock_id() {
+        let mut deps = mock_dependencies();
 
         let msg = InstantiateMsg {
             max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
         };
         let info = mock_info("creator", &[]);
         let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        // lock funds
-        let info = mock_info("anyone", &coins(2, "token"));
         let mut env = mock_env();
         env.block.time = Timestamp::from_seconds(0);
+        let info = mock_info("anyone", &coins(2, "token"));
+
+        // invalid ids
+        for id in ["", "a b", "../x", &"a".repeat(MAX_LOCK_ID_LENGTH + 1)] {
+            let msg = ExecuteMsg::Lock {
+                id: Some(id.to_string()),
+                expire: Timestamp::from_seconds(100),
+                memo: None,
+            };
+            let res = execute(deps.as_mut(), env.clone(), info.clone(), msg);
+            match res {
+                Err(ContractError::InvalidLockId { .. }) => {}
+                _ => panic!("Must return InvalidLockId error"),
+            }
+        }
+
+        // user id colliding with the next generated id
         let msg = ExecuteMsg::Lock {
-            id: "1".into(),
-            expire: Timestamp::from_seconds(400),
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(100),
+            memo: None,
         };
-        let _res = execute(deps.as_mut(), env, info, msg).unwrap();
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg).unwrap();
+        let value: LockResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!("1", value.id);
+
+        // generated ids
+        let msg = ExecuteMsg::Lock {
+            id: None,
+            expire: Timestamp::from_seconds(100),
+            memo: None,
+        };
+        let res = execute(deps.as_mut(), env.clone(), info.clone(), msg.clone()).unwrap();
+        let value: LockResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!("2", value.id);
+
+        let res = execute(deps.as_mut(), env.clone(), info, msg.clone()).unwrap();
+        let value: LockResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!("3", value.id);
+
+        // sequence by owner
+        let res = execute(
+            deps.as_mut(),
+            env,
+            mock_info("other", &coins(2, "token")),
+            msg,
+        )
+        .unwrap();
+        let value: LockResponse = from_binary(&res.data.unwrap()).unwrap();
+        assert_eq!("1", value.id);
+    }
+
+    #[test]
+    fn simulate() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
 
-        // cannot unlock until expire
-        let auth_info = mock_info("anyone", &[]);
-        let msg = ExecuteMsg::Unlock { id: "1".into() };
         let mut env = mock_env();
-        env.block.time = Timestamp::from_seconds(100);
-        let res = execute(deps.as_mut(), env.clone(), auth_info, msg);
-        match res {
-            Err(ContractError::LockNotExpired {}) => {}
-            _ => panic!("Must return LockNotExpired error"),
-        }
+        env.block.time = Timestamp::from_seconds(0);
+        let msg = ExecuteMsg::Lock {
+            id: Some("1".into()),
+            expire: Timestamp::from_seconds(400),
+            memo: Some(Memo::Commitment(HexBinary::from(
+                Sha256::digest(b"secret").to_vec(),
+            ))),
+        };
+        let _res = execute(
+            deps.as_mut(),
+            env.clone(),
+            mock_info("anyone", &coins(2, "token")),
+            msg,
+        )
+        .unwrap();
 
-        // unlock funds
-        let auth_info = mock_info("anyone", &[]);
-        let msg = ExecuteMsg::Unlock { id: "1".into() };
-        env.block.time = Timestamp::from_seconds(401);
-        let res = execute(deps.as_mut(), env, auth_info, msg).unwrap();
-        assert_eq!(1, res.messages.len());
+        // unlock before expire
+        let msg = QueryMsg::SimulateUnlock {
+            owner: "anyone".into(),
+            id: "1".into(),
+            at_time: Some(Timestamp::from_seconds(100)),
+        };
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let value: SimulateUnlockResponse = from_binary(&res).unwrap();
+        assert!(value.lock_not_expired);
+        assert!(value.reveal_required);
         assert_eq!(
-            res.messages[0],
-            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+            value.messages,
+            vec![CosmosMsg::Bank(BankMsg::Send {
                 to_address: "anyone".into(),
                 amount: coins(2, "token")
-            }))
+            })]
         );
 
-        // should lock completed
-        let msg = QueryMsg::Lock {
-            address: "anyone".into(),
+        // unlock after expire
+        let msg = QueryMsg::SimulateUnlock {
+            owner: "anyone".into(),
             id: "1".into(),
+            at_time: Some(Timestamp::from_seconds(401)),
         };
-        let res = query(deps.as_ref(), mock_env(), msg);
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let value: SimulateUnlockResponse = from_binary(&res).unwrap();
+        assert!(!value.lock_not_expired);
+
+        // lock validation
+        let simulate_lock = |id: Option<&str>, expire: u64| {
+            let msg = QueryMsg::SimulateLock {
+                owner: "anyone".into(),
+                id: id.map(String::from),
+                expire: Timestamp::from_seconds(expire),
+                at_time: None,
+            };
+            let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+            from_binary::<SimulateLockResponse>(&res).unwrap().error
+        };
+        assert_eq!(None, simulate_lock(None, 100));
+        assert_eq!(
+            Some(ContractError::LowExpired {}.to_string()),
+            simulate_lock(None, 0)
+        );
+        assert_eq!(
+            Some(ContractError::HighExpired {}.to_string()),
+            simulate_lock(None, 3601)
+        );
+        assert_eq!(
+            Some(ContractError::AlreadyInUse {}.to_string()),
+            simulate_lock(Some("1"), 100)
+        );
+    }
 
-        match res {
-            Err(StdError::NotFound { .. }) => {}
-            _ => panic!("Must return StdError::NotFound error"),
+    #[test]
+    fn enumerate_locks() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            max_lock_time: 3600,
+            deposit_fee: None,
+            tranche_config: None,
+        };
+        let info = mock_info("creator", &[]);
+        let _res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let mut env = mock_env();
+        env.block.time = Timestamp::from_seconds(0);
+        for (owner, id) in [
+            ("owner3", "1"),
+            ("owner1", "2"),
+            ("owner2", "1"),
+            ("owner1", "1"),
+        ] {
+            let msg = ExecuteMsg::Lock {
+                id: Some(id.into()),
+                expire: Timestamp::from_seconds(100),
+                memo: None,
+            };
+            let info = mock_info(owner, &coins(2, "token"));
+            let _res = execute(deps.as_mut(), env.clone(), info, msg).unwrap();
         }
+
+        // owners
+        let msg = QueryMsg::AllOwners {
+            start_after: None,
+            limit: Some(2),
+        };
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let value: AllOwnersResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            vec![Addr::unchecked("owner1"), Addr::unchecked("owner2")],
+            value.owners
+        );
+
+        let msg = QueryMsg::AllOwners {
+            start_after: Some("owner2".into()),
+            limit: None,
+        };
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let value: AllOwnersResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![Addr::unchecked("owner3")], value.owners);
+
+        // locks
+        let msg = QueryMsg::AllLocksGlobal {
+            start_after: None,
+            limit: Some(3),
+        };
+        let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+        let value: AllLocksGlobalResponse = from_binary(&res).unwrap();
+        let keys: Vec<_> = value
+            .locks
+            .iter()
+            .map(|l| (l.owner.as_str(), l.id.as_str()))
+            .collect();
+        assert_eq!(
+            vec![("owner1", "1"), ("owner1", "2"), ("owner2", "1")],
+            keys
+        );
+        assert_eq!(Timestamp::from_seconds(100), value.locks[0].expire);
+        assert_eq!(coins(2, "token"), value.locks[0].native_balance);
+
+        let msg = QueryMsg::AllLocksGlobal {
+            start_after: Some(("owner1".into(), "2".into())),
+            limit: None,
+        };
+        let res = query(deps.as_ref(), env, msg).unwrap();
+        let value: AllLocksGlobalResponse = from_binary(&res).unwrap();
+        let keys: Vec<_> = value
+            .locks
+            .iter()
+            .map(|l| (l.owner.as_str(), l.id.as_str()))
+            .collect();
+        assert_eq!(vec![("owner2", "1"), ("owner3", "1")], keys);
     }
 }
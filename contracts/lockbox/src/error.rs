@@ -1,4 +1,5 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Decimal, StdError, Uint128};
+use cw_utils::ParseReplyError;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -6,12 +7,18 @@ pub enum ContractError {
     #[error("{0}")]
     Std(#[from] StdError),
 
+    #[error("{0}")]
+    ParseReply(#[from] ParseReplyError),
+
     #[error("Unauthorized")]
     Unauthorized {},
 
     #[error("Send some coins to lock funds")]
     EmptyBalance {},
 
+    #[error("Funds must not contain zero amounts or duplicate denoms")]
+    InvalidFunds {},
+
     #[error("Expire time is lower")]
     LowExpired {},
 
@@ -26,4 +33,70 @@ pub enum ContractError {
 
     #[error("Lock id already in use")]
     AlreadyInUse {},
+
+    #[error("Lock id must have 1 to {max} alphanumeric, '-', '_' or '.' characters")]
+    InvalidLockId { max: usize },
+
+    #[error("Memo is too long (max {max} bytes)")]
+    MemoTooLong { max: usize },
+
+    #[error("Memo commitment must be a sha256 hash")]
+    InvalidCommitment {},
+
+    #[error("Revealed memo does not match commitment")]
+    InvalidReveal {},
+
+    #[error("Lock has no heir")]
+    NotInheritanceLock {},
+
+    #[error("Heir cannot be the lock owner")]
+    InvalidHeir {},
+
+    #[error("Goal amount must be greater than zero")]
+    InvalidGoal {},
+
+    #[error("Lock has no goal")]
+    NotGoalLock {},
+
+    #[error("Lock goal already reached")]
+    GoalReached {},
+
+    #[error("Lock does not accept contributors")]
+    ContributorsNotAllowed {},
+
+    #[error("Contributions must be native coins, the goal token or a token of the lock")]
+    InvalidContribution {},
+
+    #[error("Lock already has {max} contributors")]
+    TooManyContributors { max: usize },
+
+    #[error("Contribution must be at least {min} of the goal token")]
+    ContributionTooLow { min: Uint128 },
+
+    #[error("New locks are paused")]
+    Paused {},
+
+    #[error("Lock increases are paused")]
+    IncreasesPaused {},
+
+    #[error("Token {token} is paused")]
+    TokenPaused { token: String },
+
+    #[error("Deposit fee is higher than {max}")]
+    InvalidFee { max: Decimal },
+
+    #[error("Tranches are not enabled")]
+    TranchesDisabled {},
+
+    #[error("Send a single token to deposit in a tranche")]
+    InvalidTrancheFunds {},
+
+    #[error("Unknown claim token")]
+    InvalidClaimToken {},
+
+    #[error("Max lock time must be greater than zero")]
+    InvalidMaxLockTime {},
+
+    #[error("Unknown reply id {id}")]
+    UnknownReplyId { id: u64 },
 }
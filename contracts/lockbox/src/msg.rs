@@ -1,29 +1,136 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Coin, Timestamp};
-use cw20::{Cw20Coin, Cw20ReceiveMsg};
+use cosmwasm_std::{Addr, Coin, CosmosMsg, Decimal, HexBinary, Timestamp, Uint128};
+use cw20::{Cw20Coin, Cw20ReceiveMsg, Denom};
 
 #[cw_serde]
 pub struct InstantiateMsg {
     /// Max lock time in seconds
     pub max_lock_time: u64,
+    /// Fee taken from every deposit, max 10%
+    pub deposit_fee: Option<Decimal>,
+    /// Enables fungible tranches
+    pub tranche_config: Option<TrancheConfig>,
+}
+
+#[cw_serde]
+pub struct TrancheConfig {
+    /// cw20-base code id used for claim tokens
+    pub cw20_code_id: u64,
+    /// Tranche length in seconds, e.g. 2592000 for 30 days buckets
+    pub period: u64,
 }
 
 #[cw_serde]
 pub enum ExecuteMsg {
-    /// Lock funds until expire timestamp
-    Lock { id: String, expire: Timestamp },
+    /// Lock funds until expire timestamp, id is generated if empty
+    Lock {
+        id: Option<String>,
+        expire: Timestamp,
+        memo: Option<Memo>,
+    },
     /// Increase previous lock
     IncreaseLock { id: String },
-    /// Unlock funds
-    Unlock { id: String },
+    /// Unlock funds, committed memos must be revealed
+    Unlock { id: String, reveal: Option<String> },
+    /// Lock funds for an heir, claimable if the owner stops sending heartbeats
+    LockInheritance {
+        id: Option<String>,
+        heir: String,
+        inactivity_period: u64,
+    },
+    /// Extend the heartbeat deadline of an inheritance lock
+    Heartbeat { id: String },
+    /// Change the heir of an inheritance lock
+    SetHeir { id: String, heir: String },
+    /// Heir claims the funds after the heartbeat deadline
+    ClaimInheritance { owner: String, id: String },
+    /// Lock funds until the goal is reached or the expire timestamp
+    LockGoal {
+        id: Option<String>,
+        expire: Timestamp,
+        goal: Goal,
+    },
+    /// Increase the lock of another address
+    IncreaseLockFor { owner: String, id: String },
+    /// Cancel a goal lock before the goal, refunding contributors
+    CancelLock { id: String },
+    /// Allow or deny third parties to increase the lock
+    AllowContributors { id: String, allow: bool },
+    /// Pause new locks, increases or specific tokens, only owner.
+    /// Unlock is always available
+    SetPause {
+        locks: bool,
+        increases: bool,
+        tokens: Vec<String>,
+    },
+    /// Update the deposit fee, only owner
+    UpdateDepositFee { fee: Decimal },
+    /// Send the collected fees to the treasury, only owner
+    WithdrawFees { treasury: String },
+    /// Deposit funds in the tranche of the expire timestamp, minting claim tokens
+    DepositTranche { expire: Timestamp },
+    /// Update the tranche config, only owner
+    UpdateTrancheConfig { config: TrancheConfig },
     /// This accepts a properly-encoded ReceiveMsg from a cw20 contract
     Receive(Cw20ReceiveMsg),
 }
 
 #[cw_serde]
 pub enum ReceiveMsg {
-    Lock { id: String, expire: Timestamp },
-    IncreaseLock { id: String },
+    Lock {
+        id: Option<String>,
+        expire: Timestamp,
+        memo: Option<Memo>,
+    },
+    IncreaseLock {
+        id: String,
+    },
+    LockInheritance {
+        id: Option<String>,
+        heir: String,
+        inactivity_period: u64,
+    },
+    LockGoal {
+        id: Option<String>,
+        expire: Timestamp,
+        goal: Goal,
+    },
+    IncreaseLockFor {
+        owner: String,
+        id: String,
+    },
+    DepositTranche {
+        expire: Timestamp,
+    },
+    /// Redeem claim tokens for the underlying after the tranche expire
+    Redeem {},
+}
+
+/// Chain governance messages
+#[cw_serde]
+pub enum SudoMsg {
+    /// Send the lock funds back to its owner
+    ForceUnlock { owner: String, id: String },
+    /// Pause or resume new locks
+    PauseLocks { paused: bool },
+    /// Update max lock time in seconds
+    UpdateMaxLockTime { max_lock_time: u64 },
+}
+
+#[cw_serde]
+pub enum Memo {
+    /// Plain text message
+    Text(String),
+    /// Sha256 hash of the message, plaintext is revealed on unlock
+    Commitment(HexBinary),
+}
+
+#[cw_serde]
+pub struct Goal {
+    /// Native denom or cw20 address to track
+    pub denom: Denom,
+    /// Locked amount that releases the funds
+    pub amount: Uint128,
 }
 
 #[cw_serde]
@@ -35,6 +142,51 @@ pub enum QueryMsg {
     /// Returns the locks by address
     #[returns(AllLocksResponse)]
     AllLocks { address: String },
+    /// Returns the addresses with locks. Supports pagination
+    #[returns(AllOwnersResponse)]
+    AllOwners {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Returns the locks of all addresses, `start_after` is an (owner, id) pair. Supports pagination
+    #[returns(AllLocksGlobalResponse)]
+    AllLocksGlobal {
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    /// Returns the pause status
+    #[returns(PauseResponse)]
+    PauseStatus {},
+    /// Returns the deposit fee and the collected fees
+    #[returns(FeesResponse)]
+    Fees {},
+    /// Returns the tranche of the token (native denom or cw20 address) for the expire timestamp
+    #[returns(TrancheInfo)]
+    Tranche { token: String, expire: Timestamp },
+    /// Returns the tranche of a claim token
+    #[returns(TrancheInfo)]
+    ClaimToken { address: String },
+    /// Previews the Unlock of a lock at the given time (default: current block)
+    #[returns(SimulateUnlockResponse)]
+    SimulateUnlock {
+        owner: String,
+        id: String,
+        at_time: Option<Timestamp>,
+    },
+    /// Previews the Lock validation at the given time (default: current block)
+    #[returns(SimulateLockResponse)]
+    SimulateLock {
+        owner: String,
+        id: Option<String>,
+        expire: Timestamp,
+        at_time: Option<Timestamp>,
+    },
+}
+
+/// Data returned by new locks
+#[cw_serde]
+pub struct LockResponse {
+    pub id: String,
 }
 
 #[cw_serde]
@@ -46,9 +198,103 @@ pub struct LockInfo {
     pub native_balance: Vec<Coin>,
     /// Funds in cw20 tokens
     pub cw20_balance: Vec<Cw20Coin>,
+    /// Lock memo, only visible after expire
+    pub memo: Option<Memo>,
+    /// Heir settings of an inheritance lock
+    pub inheritance: Option<InheritanceInfo>,
+    /// Savings goal of the lock
+    pub goal: Option<Goal>,
+    /// Whether third parties can increase the lock
+    pub allow_contributors: bool,
+    /// Funds added by third parties
+    pub contributors: Vec<ContributorInfo>,
+    /// Deposit fee paid in native tokens
+    pub fee_native_balance: Vec<Coin>,
+    /// Deposit fee paid in cw20 tokens
+    pub fee_cw20_balance: Vec<Cw20Coin>,
+}
+
+#[cw_serde]
+pub struct ContributorInfo {
+    pub address: Addr,
+    pub native_balance: Vec<Coin>,
+    pub cw20_balance: Vec<Cw20Coin>,
+}
+
+#[cw_serde]
+pub struct InheritanceInfo {
+    pub heir: Addr,
+    pub inactivity_period: u64,
+    pub heartbeat_deadline: Timestamp,
 }
 
 #[cw_serde]
 pub struct AllLocksResponse {
     pub locks: Vec<String>,
 }
+
+#[cw_serde]
+pub struct AllOwnersResponse {
+    pub owners: Vec<Addr>,
+}
+
+#[cw_serde]
+pub struct AllLocksGlobalResponse {
+    pub locks: Vec<GlobalLockInfo>,
+}
+
+#[cw_serde]
+pub struct GlobalLockInfo {
+    pub owner: Addr,
+    pub id: String,
+    pub expire: Timestamp,
+    /// Funds in native tokens
+    pub native_balance: Vec<Coin>,
+    /// Funds in cw20 tokens
+    pub cw20_balance: Vec<Cw20Coin>,
+}
+
+#[cw_serde]
+pub struct PauseResponse {
+    /// New locks are paused
+    pub locks: bool,
+    /// Lock increases are paused
+    pub increases: bool,
+    /// Paused native denoms and cw20 addresses
+    pub tokens: Vec<String>,
+}
+
+#[cw_serde]
+pub struct FeesResponse {
+    pub deposit_fee: Decimal,
+    /// Collected fees in native tokens
+    pub native_balance: Vec<Coin>,
+    /// Collected fees in cw20 tokens
+    pub cw20_balance: Vec<Cw20Coin>,
+}
+
+#[cw_serde]
+pub struct TrancheInfo {
+    pub underlying: Denom,
+    pub expire: Timestamp,
+    /// cw20 claim token
+    pub claim_token: Option<Addr>,
+    /// Underlying amount not redeemed yet
+    pub supply: Uint128,
+}
+
+#[cw_serde]
+pub struct SimulateUnlockResponse {
+    /// Unlock would fail with LockNotExpired
+    pub lock_not_expired: bool,
+    /// The memo commitment must be revealed
+    pub reveal_required: bool,
+    /// Native and cw20 transfers sent on unlock
+    pub messages: Vec<CosmosMsg>,
+}
+
+#[cw_serde]
+pub struct SimulateLockResponse {
+    /// Error raised by the lock validation, if any
+    pub error: Option<String>,
+}
@@ -1,21 +1,88 @@
 use cosmwasm_schema::cw_serde;
 
 use crate::balance::GenericBalance;
-use cosmwasm_std::{Addr, Timestamp};
+use crate::msg::{Goal, Memo, TrancheConfig};
+use cosmwasm_std::{Addr, Decimal, Timestamp, Uint128};
+use cw20::Denom;
 use cw_storage_plus::{Item, Map};
 
 #[cw_serde]
 pub struct State {
     pub max_lock_time: u64,
     pub owner: Addr,
+    /// Fee taken from every deposit
+    #[serde(default)]
+    pub deposit_fee: Decimal,
 }
 
 #[cw_serde]
 pub struct Lock {
     pub create: Timestamp,
     pub expire: Timestamp,
+    /// Net locked funds
     pub funds: GenericBalance,
+    /// Deposit fee paid
+    #[serde(default)]
+    pub fee: GenericBalance,
+    pub memo: Option<Memo>,
+    /// Set on inheritance locks, expire is the next heartbeat deadline
+    pub inheritance: Option<Inheritance>,
+    /// Set on goal locks, funds are released once reached
+    pub goal: Option<Goal>,
+    /// Whether third parties can increase the lock
+    #[serde(default)]
+    pub allow_contributors: bool,
+    /// Funds added by third parties
+    #[serde(default)]
+    pub contributions: Vec<Contribution>,
+}
+
+#[cw_serde]
+pub struct Inheritance {
+    pub heir: Addr,
+    /// Max seconds between owner heartbeats
+    pub inactivity_period: u64,
+}
+
+#[cw_serde]
+pub struct Contribution {
+    pub contributor: Addr,
+    pub funds: GenericBalance,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub struct Pause {
+    /// New locks are not accepted
+    pub locks: bool,
+    /// Existing locks cannot be increased
+    #[serde(default)]
+    pub increases: bool,
+    /// Native denoms or cw20 addresses not accepted
+    #[serde(default)]
+    pub tokens: Vec<String>,
+}
+
+#[cw_serde]
+pub struct Tranche {
+    pub underlying: Denom,
+    pub expire: Timestamp,
+    /// cw20 claim token, set once instantiated
+    pub claim_token: Option<Addr>,
+    /// Underlying amount not redeemed yet
+    pub supply: Uint128,
 }
 
 pub const STATE: Item<State> = Item::new("state");
+pub const PAUSE: Item<Pause> = Item::new("pause");
+pub const FEES: Item<GenericBalance> = Item::new("fees");
 pub const LOCKS: Map<(&Addr, String), Lock> = Map::new("locks");
+/// Last generated lock id by owner
+pub const LOCK_SEQ: Map<&Addr, u64> = Map::new("lock_seq");
+pub const TRANCHE_CONFIG: Item<TrancheConfig> = Item::new("tranche_config");
+/// Tranches by (underlying token, expire seconds)
+pub const TRANCHES: Map<(&str, u64), Tranche> = Map::new("tranches");
+/// Tranche key by claim token
+pub const CLAIM_TOKENS: Map<&Addr, (String, u64)> = Map::new("claim_tokens");
+/// Tranche waiting for its claim token instantiation
+pub const PENDING_TRANCHE: Item<(String, u64)> = Item::new("pending_tranche");
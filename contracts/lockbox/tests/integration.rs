@@ -0,0 +1,404 @@
+use cosmwasm_std::{coins, to_binary, Addr, Coin, Empty, Timestamp, Uint128};
+use cw20::{BalanceResponse, Cw20Coin, Cw20ExecuteMsg, Cw20QueryMsg};
+use cw_multi_test::{App, AppBuilder, Contract, ContractWrapper, Executor};
+
+use cw_disper_lockbox::error::ContractError;
+use cw_disper_lockbox::msg::{
+    ExecuteMsg, InstantiateMsg, LockInfo, QueryMsg, ReceiveMsg, TrancheConfig, TrancheInfo,
+};
+
+const OWNER: &str = "owner";
+const USER: &str = "user";
+const DENOM: &str = "ujuno";
+
+fn lockbox_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw_disper_lockbox::contract::execute,
+        cw_disper_lockbox::contract::instantiate,
+        cw_disper_lockbox::contract::query,
+    )
+    .with_sudo(cw_disper_lockbox::contract::sudo)
+    .with_reply(cw_disper_lockbox::contract::reply);
+    Box::new(contract)
+}
+
+fn cw20_contract() -> Box<dyn Contract<Empty>> {
+    let contract = ContractWrapper::new(
+        cw20_base::contract::execute,
+        cw20_base::contract::instantiate,
+        cw20_base::contract::query,
+    );
+    Box::new(contract)
+}
+
+struct Suite {
+    app: App,
+    lockbox: Addr,
+    cw20: Addr,
+    cw20_id: u64,
+}
+
+impl Suite {
+    fn new() -> Self {
+        let mut app = AppBuilder::new().build(|router, _, storage| {
+            router
+                .bank
+                .init_balance(storage, &Addr::unchecked(USER), coins(1000, DENOM))
+                .unwrap();
+        });
+        app.update_block(|block| block.time = Timestamp::from_seconds(0));
+
+        let lockbox_id = app.store_code(lockbox_contract());
+        let cw20_id = app.store_code(cw20_contract());
+
+        let lockbox = app
+            .instantiate_contract(
+                lockbox_id,
+                Addr::unchecked(OWNER),
+                &InstantiateMsg {
+                    max_lock_time: 3600,
+                    deposit_fee: None,
+                    tranche_config: None,
+                },
+                &[],
+                "lockbox",
+                None,
+            )
+            .unwrap();
+
+        let cw20 = app
+            .instantiate_contract(
+                cw20_id,
+                Addr::unchecked(OWNER),
+                &cw20_base::msg::InstantiateMsg {
+                    name: "Token".into(),
+                    symbol: "TOKEN".into(),
+                    decimals: 6,
+                    initial_balances: vec![Cw20Coin {
+                        address: USER.into(),
+                        amount: Uint128::new(1000),
+                    }],
+                    mint: None,
+                    marketing: None,
+                },
+                &[],
+                "cw20",
+                None,
+            )
+            .unwrap();
+
+        Suite {
+            app,
+            lockbox,
+            cw20,
+            cw20_id,
+        }
+    }
+
+    fn lock_native(&mut self, id: &str, funds: &[Coin]) -> anyhow::Result<()> {
+        self.app.execute_contract(
+            Addr::unchecked(USER),
+            self.lockbox.clone(),
+            &ExecuteMsg::Lock {
+                id: Some(id.into()),
+                expire: Timestamp::from_seconds(100),
+                memo: None,
+            },
+            funds,
+        )?;
+        Ok(())
+    }
+
+    fn send_cw20(&mut self, amount: u128, msg: &ReceiveMsg) -> anyhow::Result<()> {
+        self.app.execute_contract(
+            Addr::unchecked(USER),
+            self.cw20.clone(),
+            &Cw20ExecuteMsg::Send {
+                contract: self.lockbox.to_string(),
+                amount: Uint128::new(amount),
+                msg: to_binary(msg)?,
+            },
+            &[],
+        )?;
+        Ok(())
+    }
+
+    fn unlock(&mut self, id: &str) -> anyhow::Result<()> {
+        self.app.execute_contract(
+            Addr::unchecked(USER),
+            self.lockbox.clone(),
+            &ExecuteMsg::Unlock {
+                id: id.into(),
+                reveal: None,
+            },
+            &[],
+        )?;
+        Ok(())
+    }
+
+    fn set_time(&mut self, seconds: u64) {
+        self.app
+            .update_block(|block| block.time = Timestamp::from_seconds(seconds));
+    }
+
+    fn query_lock(&self, id: &str) -> LockInfo {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                &self.lockbox,
+                &QueryMsg::Lock {
+                    address: USER.into(),
+                    id: id.into(),
+                },
+            )
+            .unwrap()
+    }
+
+    fn native_balance(&self, address: &str) -> u128 {
+        self.app
+            .wrap()
+            .query_balance(address, DENOM)
+            .unwrap()
+            .amount
+            .u128()
+    }
+
+    fn query_tranche(&self, expire: u64) -> TrancheInfo {
+        self.app
+            .wrap()
+            .query_wasm_smart(
+                &self.lockbox,
+                &QueryMsg::Tranche {
+                    token: DENOM.into(),
+                    expire: Timestamp::from_seconds(expire),
+                },
+            )
+            .unwrap()
+    }
+
+    fn cw20_balance(&self, address: &str) -> u128 {
+        self.token_balance(&self.cw20, address)
+    }
+
+    fn token_balance(&self, token: &Addr, address: &str) -> u128 {
+        let res: BalanceResponse = self
+            .app
+            .wrap()
+            .query_wasm_smart(
+                token,
+                &Cw20QueryMsg::Balance {
+                    address: address.into(),
+                },
+            )
+            .unwrap();
+        res.balance.u128()
+    }
+}
+
+fn assert_error(err: anyhow::Error, expected: ContractError) {
+    assert_eq!(expected.to_string(), err.root_cause().to_string());
+}
+
+#[test]
+fn cw20_lock_and_unlock() {
+    let mut suite = Suite::new();
+
+    suite
+        .send_cw20(
+            400,
+            &ReceiveMsg::Lock {
+                id: Some("1".into()),
+                expire: Timestamp::from_seconds(100),
+                memo: None,
+            },
+        )
+        .unwrap();
+    assert_eq!(600, suite.cw20_balance(USER));
+    assert_eq!(400, suite.cw20_balance(suite.lockbox.as_str()));
+
+    let lock = suite.query_lock("1");
+    assert_eq!(
+        vec![Cw20Coin {
+            address: suite.cw20.to_string(),
+            amount: Uint128::new(400),
+        }],
+        lock.cw20_balance
+    );
+
+    suite.set_time(101);
+    suite.unlock("1").unwrap();
+    assert_eq!(1000, suite.cw20_balance(USER));
+    assert_eq!(0, suite.cw20_balance(suite.lockbox.as_str()));
+}
+
+#[test]
+fn mixed_lock_and_unlock() {
+    let mut suite = Suite::new();
+
+    suite.lock_native("1", &coins(300, DENOM)).unwrap();
+    suite
+        .send_cw20(250, &ReceiveMsg::IncreaseLock { id: "1".into() })
+        .unwrap();
+    assert_eq!(700, suite.native_balance(USER));
+    assert_eq!(750, suite.cw20_balance(USER));
+
+    let lock = suite.query_lock("1");
+    assert_eq!(coins(300, DENOM), lock.native_balance);
+    assert_eq!(Uint128::new(250), lock.cw20_balance[0].amount);
+
+    suite.set_time(101);
+    suite.unlock("1").unwrap();
+    assert_eq!(1000, suite.native_balance(USER));
+    assert_eq!(1000, suite.cw20_balance(USER));
+    assert_eq!(0, suite.native_balance(suite.lockbox.as_str()));
+    assert_eq!(0, suite.cw20_balance(suite.lockbox.as_str()));
+}
+
+#[test]
+fn failure_paths() {
+    let mut suite = Suite::new();
+
+    // cw20 lock with an invalid expire keeps the tokens with the sender
+    let err = suite
+        .send_cw20(
+            100,
+            &ReceiveMsg::Lock {
+                id: Some("1".into()),
+                expire: Timestamp::from_seconds(3600),
+                memo: None,
+            },
+        )
+        .unwrap_err();
+    assert_error(err, ContractError::HighExpired {});
+    assert_eq!(1000, suite.cw20_balance(USER));
+
+    suite.lock_native("1", &coins(300, DENOM)).unwrap();
+
+    // lock id in use
+    let err = suite.lock_native("1", &coins(100, DENOM)).unwrap_err();
+    assert_error(err, ContractError::AlreadyInUse {});
+    assert_eq!(700, suite.native_balance(USER));
+
+    // cannot unlock before expire
+    let err = suite.unlock("1").unwrap_err();
+    assert_error(err, ContractError::LockNotExpired {});
+
+    // cannot increase after expire
+    suite.set_time(101);
+    let err = suite
+        .send_cw20(100, &ReceiveMsg::IncreaseLock { id: "1".into() })
+        .unwrap_err();
+    assert_error(err, ContractError::LockExpired {});
+    assert_eq!(1000, suite.cw20_balance(USER));
+
+    // only the owner can unlock
+    let err = suite
+        .app
+        .execute_contract(
+            Addr::unchecked("other"),
+            suite.lockbox.clone(),
+            &ExecuteMsg::Unlock {
+                id: "1".into(),
+                reveal: None,
+            },
+            &[],
+        )
+        .unwrap_err();
+    assert!(err.root_cause().to_string().contains("not found"));
+
+    suite.unlock("1").unwrap();
+    assert_eq!(1000, suite.native_balance(USER));
+
+    // lock is removed after unlock
+    let err = suite.unlock("1").unwrap_err();
+    assert!(err.root_cause().to_string().contains("not found"));
+}
+
+#[test]
+fn tranche_claim_tokens() {
+    let mut suite = Suite::new();
+
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(OWNER),
+            suite.lockbox.clone(),
+            &ExecuteMsg::UpdateTrancheConfig {
+                config: TrancheConfig {
+                    cw20_code_id: suite.cw20_id,
+                    period: 100,
+                },
+            },
+            &[],
+        )
+        .unwrap();
+
+    // the first deposit instantiates the claim token through the reply
+    let deposit = ExecuteMsg::DepositTranche {
+        expire: Timestamp::from_seconds(150),
+    };
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(USER),
+            suite.lockbox.clone(),
+            &deposit,
+            &coins(300, DENOM),
+        )
+        .unwrap();
+    let tranche = suite.query_tranche(150);
+    assert_eq!(Timestamp::from_seconds(200), tranche.expire);
+    assert_eq!(Uint128::new(300), tranche.supply);
+    let claim_token = tranche.claim_token.unwrap();
+    assert_eq!(300, suite.token_balance(&claim_token, USER));
+
+    // later deposits in the same tranche are minted
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(USER),
+            suite.lockbox.clone(),
+            &deposit,
+            &coins(200, DENOM),
+        )
+        .unwrap();
+    assert_eq!(Uint128::new(500), suite.query_tranche(200).supply);
+    assert_eq!(500, suite.token_balance(&claim_token, USER));
+    assert_eq!(500, suite.native_balance(USER));
+
+    // expire is validated before rounding up to the tranche end
+    suite
+        .app
+        .execute_contract(
+            Addr::unchecked(USER),
+            suite.lockbox.clone(),
+            &ExecuteMsg::DepositTranche {
+                expire: Timestamp::from_seconds(3550),
+            },
+            &coins(100, DENOM),
+        )
+        .unwrap();
+    assert_eq!(Uint128::new(100), suite.query_tranche(3600).supply);
+
+    let redeem = Cw20ExecuteMsg::Send {
+        contract: suite.lockbox.to_string(),
+        amount: Uint128::new(200),
+        msg: to_binary(&ReceiveMsg::Redeem {}).unwrap(),
+    };
+    let err = suite
+        .app
+        .execute_contract(Addr::unchecked(USER), claim_token.clone(), &redeem, &[])
+        .unwrap_err();
+    assert_error(err, ContractError::LockNotExpired {});
+
+    // claim tokens are burned for the underlying after expire
+    suite.set_time(201);
+    suite
+        .app
+        .execute_contract(Addr::unchecked(USER), claim_token.clone(), &redeem, &[])
+        .unwrap();
+    assert_eq!(600, suite.native_balance(USER));
+    assert_eq!(300, suite.token_balance(&claim_token, USER));
+    assert_eq!(0, suite.token_balance(&claim_token, suite.lockbox.as_str()));
+    assert_eq!(Uint128::new(300), suite.query_tranche(200).supply);
+}
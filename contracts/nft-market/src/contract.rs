@@ -1,17 +1,31 @@
 use cosmwasm_std::{
-    coin, entry_point, from_binary, to_binary, BankMsg, Binary, CosmosMsg, Decimal, Deps, DepsMut,
-    Env, MessageInfo, Order, Response, StdResult, Uint128, WasmMsg,
+    attr, coin, entry_point, from_binary, to_binary, Addr, Attribute, BankMsg, Binary, Coin,
+    CosmosMsg, Decimal, Deps, DepsMut, Empty, Env, MessageInfo, Order, Response, StdError,
+    StdResult, Storage, Timestamp, Uint128, WasmMsg,
 };
 
-use crate::cw721::{Cw721ExecuteMsg, Cw721ReceiveMsg};
+use crate::cw721::{
+    Cw2981Extension, Cw2981QueryMsg, Cw721ExecuteMsg, Cw721QueryMsg, Cw721ReceiveMsg,
+    OwnerOfResponse, RoyaltiesInfoResponse,
+};
 use crate::error::ContractError;
 use crate::msg::{
-    CountResponse, ExecuteMsg, FeeResponse, InstantiateMsg, Offer, OffersResponse, QueryMsg,
-    SellNft,
+    AccruedFeesResponse, AuctionInfo, AuctionNft, AuctionsResponse, Bid, BidsResponse,
+    CollectionBidInfo, CollectionBidsResponse, CountResponse, CurrentPriceResponse, ExecuteMsg,
+    FeeResponse, InstantiateMsg, MigrateMsg, Offer, OfferResponse, OffersResponse, OrderBy,
+    PendingFee, PendingFeeResponse, Price, Pricing, QueryMsg, ReceiveMsg, ReceiveNftMsg,
+    ReceiveNftPayload, SellNft, TokenOffer, TokenOffersResponse,
+};
+use crate::state::{
+    auction_key, collection_bid_key, collection_bids, get_fund, increment_auctions,
+    increment_collection_bids, increment_offerings, maybe_addr, offering_key, offerings,
+    token_offers, Auction, CollectionBid, Offering, State, AUCTIONS, AUCTION_BIDS, FEES,
+    LEGACY_OFFERINGS, LEGACY_OFFERING_INDEXES, PENDING_FEE, STATE,
 };
-use crate::state::{get_fund, increment_offerings, Offering, State, OFFERINGS, STATE};
-use cw2::set_contract_version;
-use cw_storage_plus::Bound;
+use cw2::{get_contract_version, set_contract_version};
+use cw20::{Cw20Coin, Cw20ExecuteMsg, Cw20ReceiveMsg};
+use cw_storage_plus::{Bound, Map};
+use std::convert::TryInto;
 use std::ops::{Mul, Sub};
 
 // version info for migration info
@@ -20,6 +34,12 @@ const CONTRACT_VERSION: &str = env!("CARGO_PKG_VERSION");
 
 const DEFAULT_LIMIT: u32 = 10;
 const MAX_LIMIT: u32 = 30;
+/// Auctions read by one `ActiveAuctions` page, ended ones included
+const MAX_AUCTION_SCAN: usize = 100;
+/// Bids read by one `TopCollectionBids` page, expired ones included
+const MAX_COLLECTION_BID_SCAN: usize = 100;
+/// Legacy offerings and index entries moved by one `MigrateOfferings`
+const MAX_MIGRATE_LIMIT: u32 = 100;
 
 #[entry_point]
 pub fn instantiate(
@@ -30,94 +50,436 @@ pub fn instantiate(
 ) -> Result<Response, ContractError> {
     set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
 
+    let max_fee = msg.max_fee.unwrap_or_else(Decimal::one);
+    validate_fee(max_fee, Decimal::one())?;
+    validate_fee(msg.fee, max_fee)?;
+
     let state = State {
         num_offerings: 0,
         fee: msg.fee,
         owner: info.sender,
+        max_fee,
+        fee_timelock: msg.fee_timelock.unwrap_or_default(),
+        num_auctions: 0,
+        num_collection_bids: 0,
     };
     STATE.save(deps.storage, &state)?;
 
     Ok(Response::default())
 }
 
+#[entry_point]
+pub fn migrate(deps: DepsMut, _env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    let version = get_contract_version(deps.storage)?;
+    if version.contract != CONTRACT_NAME {
+        return Err(ContractError::InvalidMigration {
+            contract: version.contract,
+        });
+    }
+
+    // legacy offerings are moved afterwards in batches, see `MigrateOfferings`
+    set_contract_version(deps.storage, CONTRACT_NAME, CONTRACT_VERSION)?;
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("from_version", version.version))
+}
+
 // And declare a custom Error variant for the ones where you will want to make use of it
 #[entry_point]
 pub fn execute(
     deps: DepsMut,
-    _env: Env,
+    env: Env,
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    // trading waits for the legacy offerings
+    let trading = !matches!(msg, ExecuteMsg::MigrateOfferings { .. });
+    if trading && !LEGACY_OFFERINGS.is_empty(deps.storage) {
+        return Err(ContractError::MigrationPending {});
+    }
+    apply_pending_fee(deps.storage, &env)?;
+
     match msg {
-        ExecuteMsg::Buy { offering_id } => execute_buy(deps, info, offering_id),
+        ExecuteMsg::Buy { offering_id } => execute_buy(deps, env, info, offering_id),
         ExecuteMsg::WithdrawNft { offering_id } => execute_withdraw(deps, info, offering_id),
-        ExecuteMsg::ReceiveNft(msg) => execute_receive_nft(deps, info, msg),
-        ExecuteMsg::WithdrawFees { amount, denom } => {
-            execute_withdraw_fees(deps, info, amount, denom)
+        ExecuteMsg::UpdatePrice {
+            offering_id,
+            list_price,
+        } => execute_update_price(deps, info, offering_id, list_price),
+        ExecuteMsg::ReturnExpired { offering_id } => execute_return_expired(deps, env, offering_id),
+        ExecuteMsg::ReceiveNft(msg) => execute_receive_nft(deps, env, info, msg),
+        ExecuteMsg::Receive(msg) => execute_receive(deps, env, info, msg),
+        ExecuteMsg::PlaceBid { auction_id } => execute_place_bid(deps, env, info, auction_id),
+        ExecuteMsg::SettleAuction { auction_id } => execute_settle_auction(deps, env, auction_id),
+        ExecuteMsg::MakeOffer {
+            nft_contract,
+            token_id,
+            expires,
+        } => execute_make_offer(deps, env, info, nft_contract, token_id, expires),
+        ExecuteMsg::CancelOffer {
+            nft_contract,
+            token_id,
+            bidder,
+        } => execute_cancel_offer(deps, env, info, nft_contract, token_id, bidder),
+        ExecuteMsg::AcceptOffer {
+            nft_contract,
+            token_id,
+            bidder,
+        } => execute_accept_offer(deps, env, info, nft_contract, token_id, bidder),
+        ExecuteMsg::MakeCollectionBid {
+            nft_contract,
+            price,
+            quantity,
+            expires,
+        } => execute_make_collection_bid(deps, env, info, nft_contract, price, quantity, expires),
+        ExecuteMsg::CancelCollectionBid { bid_id } => {
+            execute_cancel_collection_bid(deps, env, info, bid_id)
+        }
+        ExecuteMsg::WithdrawFees {
+            amount,
+            denom,
+            recipient,
+        } => execute_withdraw_fees(deps, info, amount, denom, recipient),
+        ExecuteMsg::WithdrawAllFees { recipient } => {
+            execute_withdraw_all_fees(deps, info, recipient)
+        }
+        ExecuteMsg::ChangeFee { fee } => execute_change_fee(deps, env, info, fee),
+        ExecuteMsg::MigrateOfferings { limit } => execute_migrate_offerings(deps, limit),
+    }
+}
+
+pub fn execute_migrate_offerings(
+    deps: DepsMut,
+    limit: Option<u32>,
+) -> Result<Response, ContractError> {
+    let limit = limit.unwrap_or(MAX_MIGRATE_LIMIT).min(MAX_MIGRATE_LIMIT) as usize;
+
+    // offerings keyed by the string of their id move to u64 keys, which also indexes them
+    let legacy = LEGACY_OFFERINGS
+        .range(deps.storage, None, None, Order::Ascending)
+        .take(limit)
+        .collect::<StdResult<Vec<_>>>()?;
+    for (id, off) in &legacy {
+        offerings().save(deps.storage, offering_key(id)?, off)?;
+        LEGACY_OFFERINGS.remove(deps.storage, id);
+    }
+
+    // the rest of the batch clears the legacy indexes
+    let mut cleared = 0;
+    for namespace in LEGACY_OFFERING_INDEXES {
+        let index = Map::<Vec<u8>, Empty>::new(namespace);
+        let keys = index
+            .keys(deps.storage, None, None, Order::Ascending)
+            .take(limit - legacy.len() - cleared)
+            .collect::<StdResult<Vec<_>>>()?;
+        for key in keys.iter() {
+            index.remove(deps.storage, key.clone());
         }
-        ExecuteMsg::ChangeFee { fee } => execute_change_fee(deps, info, fee),
+        cleared += keys.len();
     }
+
+    Ok(Response::new()
+        .add_attribute("action", "migrate_offerings")
+        .add_attribute("offerings", legacy.len().to_string())
+        .add_attribute("index_entries", cleared.to_string()))
 }
 
 pub fn execute_buy(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     offering_id: String,
 ) -> Result<Response, ContractError> {
     // check if offering exists
-    let off = OFFERINGS.load(deps.storage, &offering_id)?;
+    let off = offerings().load(deps.storage, offering_key(&offering_id)?)?;
+
+    let paid = match &off.list_price {
+        Price::Native(price) => get_fund(info.funds.clone(), price.denom.clone())?,
+        Price::Cw20(_) => return Err(ContractError::InvalidToken {}),
+    };
+    // coins unrelated to the price are sent back to the buyer
+    let others = info
+        .funds
+        .into_iter()
+        .filter(|fund| fund.denom != paid.denom)
+        .collect();
+
+    buy_offering(
+        deps,
+        env,
+        info.sender,
+        offering_id,
+        off,
+        paid.into(),
+        others,
+    )
+}
 
-    if off.seller.eq(&info.sender) {
+pub fn execute_receive(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw20ReceiveMsg,
+) -> Result<Response, ContractError> {
+    let msg: ReceiveMsg = from_binary(&wrapper.msg)?;
+    let buyer = deps.api.addr_validate(&wrapper.sender)?;
+    let token = info.sender;
+    let paid = Price::Cw20(Cw20Coin {
+        address: token.to_string(),
+        amount: wrapper.amount,
+    });
+
+    match msg {
+        ReceiveMsg::Buy { offering_id } => {
+            let off = offerings().load(deps.storage, offering_key(&offering_id)?)?;
+            if !matches!(off.list_price, Price::Cw20(ref c) if c.address == token) {
+                return Err(ContractError::InvalidToken {});
+            }
+
+            buy_offering(deps, env, buyer, offering_id, off, paid, vec![])
+        }
+        ReceiveMsg::PlaceBid { auction_id } => {
+            let auction_id = auction_key(&auction_id)?;
+            let auction = AUCTIONS.load(deps.storage, auction_id)?;
+            if !matches!(auction.reserve_price, Price::Cw20(ref c) if c.address == token) {
+                return Err(ContractError::InvalidToken {});
+            }
+
+            place_bid(deps, env, buyer, auction_id, auction, paid, vec![])
+        }
+        ReceiveMsg::MakeOffer {
+            nft_contract,
+            token_id,
+            expires,
+        } => {
+            if paid.amount().is_zero() {
+                return Err(ContractError::InvalidFunds {});
+            }
+
+            // any cw20 contract can claim the bidder, it must not replace an escrowed offer
+            let nft = deps.api.addr_validate(&nft_contract)?;
+            if token_offers().has(deps.storage, (&nft, token_id.as_str(), &buyer)) {
+                return Err(ContractError::OfferExists {});
+            }
+
+            make_offer(deps, env, buyer, nft_contract, token_id, expires, paid)
+        }
+        ReceiveMsg::MakeCollectionBid {
+            nft_contract,
+            price,
+            quantity,
+            expires,
+        } => {
+            let price = paid.with_amount(price);
+            validate_escrow(&price, quantity, paid.amount())?;
+
+            make_collection_bid(deps, env, buyer, nft_contract, price, quantity, expires)
+        }
+    }
+}
+
+fn buy_offering(
+    mut deps: DepsMut,
+    env: Env,
+    buyer: Addr,
+    offering_id: String,
+    off: Offering,
+    paid: Price,
+    mut refund: Vec<Coin>,
+) -> Result<Response, ContractError> {
+    if off.seller.eq(&buyer) {
         return Err(ContractError::InvalidBuyer {});
     }
+    if off.is_expired(env.block.time) {
+        return Err(ContractError::ListingExpired {});
+    }
 
     // check for enough coins
-    let off_fund = get_fund(info.funds.clone(), off.list_price.denom)?;
-    if off_fund.amount < off.list_price.amount {
+    let price = off.current_price(env.block.time);
+    if paid.amount() < price.amount() {
         return Err(ContractError::InsufficientFunds {});
     }
 
-    let state = STATE.load(deps.storage)?;
-    let net_amount = Decimal::one().sub(state.fee).mul(off_fund.amount);
-    // create transfer msg
-    let transfer_msg: CosmosMsg = BankMsg::Send {
-        to_address: off.seller.clone().into(),
-        amount: vec![coin(net_amount.u128(), off_fund.denom.clone())],
-    }
-    .into();
+    let surplus = paid.amount() - price.amount();
+    let (mut messages, payout) = settle_sale(
+        deps.branch(),
+        &off.contract,
+        &off.token_id,
+        &price,
+        &off.seller,
+    )?;
 
     // create transfer cw721 msg
     let cw721_transfer = Cw721ExecuteMsg::TransferNft {
-        recipient: info.sender.clone().into(),
+        recipient: buyer.clone().into(),
         token_id: off.token_id.clone(),
     };
-    let cw721_transfer_msg: CosmosMsg = WasmMsg::Execute {
-        contract_addr: off.contract.clone().into(),
-        msg: to_binary(&cw721_transfer)?,
-        funds: vec![],
+    messages.push(
+        WasmMsg::Execute {
+            contract_addr: off.contract.clone().into(),
+            msg: to_binary(&cw721_transfer)?,
+            funds: vec![],
+        }
+        .into(),
+    );
+
+    // refund the overpayment and the unrelated coins
+    let mut refunds = vec![];
+    if !surplus.is_zero() {
+        match &paid {
+            Price::Native(paid) => refund.insert(0, coin(surplus.u128(), &paid.denom)),
+            Price::Cw20(_) => {
+                messages.push(transfer_price(&paid, &buyer, surplus)?);
+                refunds.push(format!("{}{}", surplus, paid.denom()));
+            }
+        }
+    }
+    if !refund.is_empty() {
+        refunds.extend(refund.iter().map(|c| c.to_string()));
+        messages.push(
+            BankMsg::Send {
+                to_address: buyer.clone().into(),
+                amount: refund,
+            }
+            .into(),
+        );
     }
-    .into();
 
-    OFFERINGS.remove(deps.storage, &offering_id);
+    offerings().remove(deps.storage, offering_key(&offering_id)?)?;
 
-    let price_string = format!("{}{}", off_fund.amount, off_fund.denom);
-    let res = Response::new()
+    let mut res = Response::new()
         .add_attribute("action", "buy_nft")
-        .add_attribute("buyer", info.sender)
+        .add_attribute("buyer", buyer)
         .add_attribute("seller", off.seller)
-        .add_attribute("paid_price", price_string)
+        .add_attribute("paid_price", price.to_string())
         .add_attribute("token_id", off.token_id)
         .add_attribute("nft_contract", off.contract)
-        .add_messages(vec![transfer_msg, cw721_transfer_msg]);
+        .add_attributes(payout)
+        .add_messages(messages);
+    if !refunds.is_empty() {
+        res = res.add_attribute("refund", refunds.join(","));
+    }
     Ok(res)
 }
 
+/// Splits the sale price between the marketplace fee, the creator royalty and the seller.
+/// The fee stays in the contract
+fn settle_sale(
+    deps: DepsMut,
+    nft_contract: &Addr,
+    token_id: &str,
+    price: &Price,
+    seller: &Addr,
+) -> Result<(Vec<CosmosMsg>, Vec<Attribute>), ContractError> {
+    let state = STATE.load(deps.storage)?;
+    let net_amount = Decimal::one().sub(state.fee).mul(price.amount());
+    let fee = price.amount() - net_amount;
+    if !fee.is_zero() {
+        accrue_fee(deps.storage, price, fee)?;
+    }
+
+    let mut messages = vec![];
+    let mut attributes = vec![attr("fee_amount", price.with_amount(fee).to_string())];
+    let mut seller_amount = net_amount;
+    if let Some((recipient, royalty)) =
+        query_royalty(deps.as_ref(), nft_contract, token_id, price.amount())
+    {
+        let royalty = royalty.min(net_amount);
+        seller_amount -= royalty;
+        messages.push(transfer_price(price, &recipient, royalty)?);
+        attributes.push(attr("royalty_recipient", recipient));
+        attributes.push(attr(
+            "royalty_amount",
+            price.with_amount(royalty).to_string(),
+        ));
+    }
+    if !seller_amount.is_zero() {
+        messages.insert(0, transfer_price(price, seller, seller_amount)?);
+    }
+    attributes.insert(
+        0,
+        attr(
+            "seller_amount",
+            price.with_amount(seller_amount).to_string(),
+        ),
+    );
+
+    Ok((messages, attributes))
+}
+
+/// Queries the CW2981 royalty of the token, none if the contract does not support it
+fn query_royalty(
+    deps: Deps,
+    nft_contract: &Addr,
+    token_id: &str,
+    sale_price: Uint128,
+) -> Option<(Addr, Uint128)> {
+    let msg = Cw2981QueryMsg::Extension {
+        msg: Cw2981Extension::RoyaltyInfo {
+            token_id: token_id.to_string(),
+            sale_price,
+        },
+    };
+    let res: RoyaltiesInfoResponse = deps.querier.query_wasm_smart(nft_contract, &msg).ok()?;
+    if res.royalty_amount.is_zero() {
+        return None;
+    }
+    let recipient = deps.api.addr_validate(&res.address).ok()?;
+    Some((recipient, res.royalty_amount))
+}
+
+fn accrue_fee(storage: &mut dyn Storage, price: &Price, amount: Uint128) -> StdResult<()> {
+    FEES.update(storage, price.denom(), |fee| -> StdResult<_> {
+        let total = match fee {
+            Some(fee) => fee.amount().checked_add(amount)?,
+            None => amount,
+        };
+        Ok(price.with_amount(total))
+    })?;
+    Ok(())
+}
+
+fn transfer_nft(contract: &Addr, recipient: &Addr, token_id: &str) -> StdResult<CosmosMsg> {
+    let transfer = Cw721ExecuteMsg::TransferNft {
+        recipient: recipient.to_string(),
+        token_id: token_id.to_string(),
+    };
+    Ok(WasmMsg::Execute {
+        contract_addr: contract.to_string(),
+        msg: to_binary(&transfer)?,
+        funds: vec![],
+    }
+    .into())
+}
+
+/// Sends an amount of the price token
+fn transfer_price(price: &Price, to: &Addr, amount: Uint128) -> StdResult<CosmosMsg> {
+    let msg = match price {
+        Price::Native(price) => BankMsg::Send {
+            to_address: to.into(),
+            amount: vec![coin(amount.u128(), &price.denom)],
+        }
+        .into(),
+        Price::Cw20(price) => WasmMsg::Execute {
+            contract_addr: price.address.clone(),
+            msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                recipient: to.into(),
+                amount,
+            })?,
+            funds: vec![],
+        }
+        .into(),
+    };
+    Ok(msg)
+}
+
 pub fn execute_withdraw(
     deps: DepsMut,
     info: MessageInfo,
     offering_id: String,
 ) -> Result<Response, ContractError> {
-    let off = OFFERINGS.load(deps.storage, &offering_id)?;
+    let off = offerings().load(deps.storage, offering_key(&offering_id)?)?;
     if off.seller.ne(&info.sender) {
         return Err(ContractError::Unauthorized {});
     }
@@ -134,7 +496,7 @@ pub fn execute_withdraw(
     }
     .into();
 
-    OFFERINGS.remove(deps.storage, &offering_id);
+    offerings().remove(deps.storage, offering_key(&offering_id)?)?;
 
     let res = Response::new()
         .add_attribute("action", "withdraw_nft")
@@ -143,13 +505,79 @@ pub fn execute_withdraw(
     Ok(res)
 }
 
+pub fn execute_update_price(
+    deps: DepsMut,
+    info: MessageInfo,
+    offering_id: String,
+    list_price: Price,
+) -> Result<Response, ContractError> {
+    let mut off = offerings().load(deps.storage, offering_key(&offering_id)?)?;
+    if off.seller.ne(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    validate_listing(deps.as_ref(), &list_price, &off.pricing)?;
+    off.list_price = list_price;
+    offerings().save(deps.storage, offering_key(&offering_id)?, &off)?;
+
+    let res = Response::new()
+        .add_attribute("action", "update_price")
+        .add_attribute("offering_id", offering_id)
+        .add_attribute("list_price", off.list_price.to_string());
+    Ok(res)
+}
+
+pub fn execute_return_expired(
+    deps: DepsMut,
+    env: Env,
+    offering_id: String,
+) -> Result<Response, ContractError> {
+    let off = offerings().load(deps.storage, offering_key(&offering_id)?)?;
+    if !off.is_expired(env.block.time) {
+        return Err(ContractError::ListingNotExpired {});
+    }
+
+    offerings().remove(deps.storage, offering_key(&offering_id)?)?;
+
+    let res = Response::new()
+        .add_attribute("action", "return_expired")
+        .add_attribute("offering_id", offering_id)
+        .add_attribute("seller", off.seller.clone())
+        .add_message(transfer_nft(&off.contract, &off.seller, &off.token_id)?);
+    Ok(res)
+}
+
 pub fn execute_receive_nft(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
     wrapper: Cw721ReceiveMsg,
 ) -> Result<Response, ContractError> {
-    let msg: SellNft = from_binary(&wrapper.msg)?;
-    let id = increment_offerings(deps.storage)?.to_string();
+    let msg = match from_binary(&wrapper.msg)? {
+        ReceiveNftPayload::Msg(msg) => msg,
+        ReceiveNftPayload::LegacySell(msg) => ReceiveNftMsg::Sell(msg),
+    };
+    let msg: SellNft = match msg {
+        ReceiveNftMsg::Sell(msg) => msg,
+        ReceiveNftMsg::Auction(msg) => {
+            return execute_create_auction(deps, env, info, wrapper, msg);
+        }
+        ReceiveNftMsg::AcceptOffer(msg) => {
+            let seller = deps.api.addr_validate(&wrapper.sender)?;
+            return accept_offer(deps, env, seller, info.sender, wrapper.token_id, msg.bidder);
+        }
+        ReceiveNftMsg::FillCollectionBid(msg) => {
+            return execute_fill_collection_bid(deps, env, info, wrapper, msg.bid_id);
+        }
+    };
+    let pricing = msg.pricing.unwrap_or_default();
+    validate_listing(deps.as_ref(), &msg.list_price, &pricing)?;
+    if let Some(expires) = msg.expires {
+        if expires <= env.block.time {
+            return Err(ContractError::InvalidExpiration {});
+        }
+    }
+    let id = increment_offerings(deps.storage)?;
 
     // save Offering
     let off = Offering {
@@ -157,270 +585,2876 @@ pub fn execute_receive_nft(
         token_id: wrapper.token_id,
         seller: deps.api.addr_validate(&wrapper.sender)?,
         list_price: msg.list_price.clone(),
+        pricing,
+        expires: msg.expires,
     };
-    OFFERINGS.save(deps.storage, &id, &off)?;
+    offerings().save(deps.storage, id, &off)?;
 
-    let price_string = format!("{}{}", msg.list_price.amount, msg.list_price.denom);
     let res = Response::new()
         .add_attribute("action", "sell_nft")
-        .add_attribute("offering_id", id)
+        .add_attribute("offering_id", id.to_string())
         .add_attribute("nft_contract", info.sender)
         .add_attribute("seller", off.seller)
-        .add_attribute("list_price", price_string)
+        .add_attribute("list_price", msg.list_price.to_string())
         .add_attribute("token_id", off.token_id);
     Ok(res)
 }
 
-pub fn execute_withdraw_fees(
+fn validate_listing(
+    deps: Deps,
+    list_price: &Price,
+    pricing: &Pricing,
+) -> Result<(), ContractError> {
+    if let Price::Cw20(coin) = list_price {
+        deps.api.addr_validate(&coin.address)?;
+    }
+    if let Pricing::Dutch {
+        floor_price,
+        start_time,
+        end_time,
+    } = pricing
+    {
+        if floor_price > &list_price.amount() || end_time <= start_time {
+            return Err(ContractError::InvalidPricing {});
+        }
+    }
+    Ok(())
+}
+
+pub fn execute_create_auction(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    amount: Uint128,
-    denom: String,
+    wrapper: Cw721ReceiveMsg,
+    msg: AuctionNft,
 ) -> Result<Response, ContractError> {
-    let state = STATE.load(deps.storage)?;
-
-    if state.owner.ne(&info.sender) {
-        return Err(ContractError::Unauthorized {});
+    if let Price::Cw20(coin) = &msg.reserve_price {
+        deps.api.addr_validate(&coin.address)?;
     }
-
-    let transfer: CosmosMsg = BankMsg::Send {
-        to_address: state.owner.into(),
-        amount: vec![coin(amount.into(), denom)],
+    if msg.end_time <= msg.start_time || msg.end_time <= env.block.time {
+        return Err(ContractError::InvalidAuction {});
     }
-    .into();
+    if msg.min_increment.is_zero() {
+        return Err(ContractError::InvalidIncrement {});
+    }
+    let id = increment_auctions(deps.storage)?;
+
+    let auction = Auction {
+        token_id: wrapper.token_id,
+        contract: info.sender,
+        seller: deps.api.addr_validate(&wrapper.sender)?,
+        reserve_price: msg.reserve_price,
+        start_time: msg.start_time,
+        end_time: msg.end_time,
+        min_increment: msg.min_increment,
+        extension: msg.extension.unwrap_or_default(),
+        highest_bid: None,
+        num_bids: 0,
+    };
+    AUCTIONS.save(deps.storage, id, &auction)?;
 
-    Ok(Response::new().add_message(transfer))
+    let res = Response::new()
+        .add_attribute("action", "create_auction")
+        .add_attribute("auction_id", id.to_string())
+        .add_attribute("nft_contract", auction.contract)
+        .add_attribute("seller", auction.seller)
+        .add_attribute("reserve_price", auction.reserve_price.to_string())
+        .add_attribute("token_id", auction.token_id)
+        .add_attribute("start_time", auction.start_time.to_string())
+        .add_attribute("end_time", auction.end_time.to_string());
+    Ok(res)
 }
 
-pub fn execute_change_fee(
+pub fn execute_place_bid(
     deps: DepsMut,
+    env: Env,
     info: MessageInfo,
-    fee: Decimal,
+    auction_id: String,
 ) -> Result<Response, ContractError> {
-    STATE.update(deps.storage, |mut state| -> Result<_, ContractError> {
-        if state.owner.ne(&info.sender) {
-            return Err(ContractError::Unauthorized {});
-        }
+    let auction_id = auction_key(&auction_id)?;
+    let auction = AUCTIONS.load(deps.storage, auction_id)?;
 
-        state.fee = fee;
-        Ok(state)
-    })?;
+    let paid = match &auction.reserve_price {
+        Price::Native(price) => get_fund(info.funds.clone(), price.denom.clone())?,
+        Price::Cw20(_) => return Err(ContractError::InvalidToken {}),
+    };
+    // coins unrelated to the bid are sent back to the bidder
+    let others = info
+        .funds
+        .into_iter()
+        .filter(|fund| fund.denom != paid.denom)
+        .collect();
 
-    let res = Response::new()
-        .add_attribute("action", "change_fee")
-        .add_attribute("fee", fee.to_string());
-    Ok(res)
+    place_bid(
+        deps,
+        env,
+        info.sender,
+        auction_id,
+        auction,
+        paid.into(),
+        others,
+    )
 }
 
-#[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
-    match msg {
-        QueryMsg::GetCount {} => to_binary(&query_count(deps)?),
-        QueryMsg::GetFee {} => to_binary(&query_fee(deps)?),
-        QueryMsg::AllOffers { start_after, limit } => {
-            to_binary(&query_all(deps, start_after, limit)?)
-        }
+fn place_bid(
+    deps: DepsMut,
+    env: Env,
+    bidder: Addr,
+    auction_id: u64,
+    mut auction: Auction,
+    paid: Price,
+    refund: Vec<Coin>,
+) -> Result<Response, ContractError> {
+    let now = env.block.time;
+    if now < auction.start_time || now >= auction.end_time {
+        return Err(ContractError::AuctionNotActive {});
+    }
+    if auction.seller.eq(&bidder) {
+        return Err(ContractError::InvalidBuyer {});
     }
-}
 
-fn query_count(deps: Deps) -> StdResult<CountResponse> {
-    let state = STATE.load(deps.storage)?;
-    Ok(CountResponse {
-        count: state.num_offerings,
-    })
-}
+    let min = match &auction.highest_bid {
+        Some(bid) => bid
+            .amount
+            .checked_add(auction.min_increment)
+            .map_err(StdError::from)?,
+        None => auction.reserve_price.amount(),
+    };
+    if paid.amount() < min {
+        return Err(ContractError::BidTooLow { min });
+    }
 
-fn query_fee(deps: Deps) -> StdResult<FeeResponse> {
-    let state = STATE.load(deps.storage)?;
-    Ok(FeeResponse { fee: state.fee })
-}
+    // the outbid bidder is refunded
+    let mut messages = vec![];
+    if let Some(bid) = &auction.highest_bid {
+        messages.push(transfer_price(&paid, &bid.bidder, bid.amount)?);
+    }
+    if !refund.is_empty() {
+        messages.push(
+            BankMsg::Send {
+                to_address: bidder.to_string(),
+                amount: refund,
+            }
+            .into(),
+        );
+    }
 
-fn query_all(
-    deps: Deps,
-    start_after: Option<String>,
-    limit: Option<u32>,
-) -> StdResult<OffersResponse> {
-    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
-    let start = start_after.map(|s| Bound::ExclusiveRaw(s.into_bytes()));
+    // anti-sniping, late bids extend the auction
+    if auction.end_time.seconds() - now.seconds() < auction.extension {
+        auction.end_time = now.plus_seconds(auction.extension);
+    }
 
-    let offers: StdResult<Vec<Offer>> = OFFERINGS
-        .range(deps.storage, start, None, Order::Ascending)
-        .take(limit)
-        .map(|item| item.map(map_offer))
-        .collect();
+    let bid = Bid {
+        bidder,
+        amount: paid.amount(),
+        time: now,
+    };
+    AUCTION_BIDS.save(deps.storage, (auction_id, auction.num_bids), &bid)?;
+    auction.num_bids += 1;
+    auction.highest_bid = Some(bid.clone());
+    AUCTIONS.save(deps.storage, auction_id, &auction)?;
 
-    Ok(OffersResponse { offers: offers? })
+    let res = Response::new()
+        .add_attribute("action", "place_bid")
+        .add_attribute("auction_id", auction_id.to_string())
+        .add_attribute("bidder", bid.bidder)
+        .add_attribute("amount", paid.to_string())
+        .add_attribute("end_time", auction.end_time.to_string())
+        .add_messages(messages);
+    Ok(res)
 }
 
-fn map_offer((k, v): (String, Offering)) -> Offer {
-    Offer {
-        id: k,
-        token_id: v.token_id,
-        contract: v.contract,
-        seller: v.seller,
-        list_price: v.list_price,
+pub fn execute_settle_auction(
+    mut deps: DepsMut,
+    env: Env,
+    auction_id: String,
+) -> Result<Response, ContractError> {
+    let id = auction_key(&auction_id)?;
+    let auction = AUCTIONS.load(deps.storage, id)?;
+    if env.block.time < auction.end_time {
+        return Err(ContractError::AuctionNotEnded {});
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use cosmwasm_std::testing::{
-        mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info,
+    AUCTIONS.remove(deps.storage, id);
+
+    let res = Response::new()
+        .add_attribute("action", "settle_auction")
+        .add_attribute("auction_id", auction_id)
+        .add_attribute("token_id", auction.token_id.clone())
+        .add_attribute("nft_contract", auction.contract.clone())
+        .add_attribute("seller", auction.seller.clone());
+
+    // without bids the NFT goes back to the seller
+    let bid = match auction.highest_bid {
+        Some(bid) => bid,
+        None => {
+            let transfer = transfer_nft(&auction.contract, &auction.seller, &auction.token_id)?;
+            return Ok(res.add_message(transfer));
+        }
     };
-    use cosmwasm_std::{coins, Decimal, SubMsg};
 
-    fn setup(deps: DepsMut) {
-        let msg = InstantiateMsg {
-            fee: Decimal::percent(2),
-        };
-        let info = mock_info("creator", &[]);
+    let price = auction.reserve_price.with_amount(bid.amount);
+    let (messages, payout) = settle_sale(
+        deps.branch(),
+        &auction.contract,
+        &auction.token_id,
+        &price,
+        &auction.seller,
+    )?;
+    let transfer = transfer_nft(&auction.contract, &bid.bidder, &auction.token_id)?;
 
-        // we can just call .unwrap() to assert this was a success
-        instantiate(deps, mock_env(), info, msg).unwrap();
-    }
+    Ok(res
+        .add_attribute("winner", bid.bidder)
+        .add_attribute("price", price.to_string())
+        .add_attributes(payout)
+        .add_messages(messages)
+        .add_message(transfer))
+}
 
-    #[test]
-    fn proper_initialization() {
-        let mut deps = mock_dependencies();
+pub fn execute_make_offer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    nft_contract: String,
+    token_id: String,
+    expires: Timestamp,
+) -> Result<Response, ContractError> {
+    let price = match info.funds.as_slice() {
+        [fund] if !fund.amount.is_zero() => Price::Native(fund.clone()),
+        _ => return Err(ContractError::InvalidFunds {}),
+    };
 
-        let msg = InstantiateMsg {
-            fee: Decimal::percent(2),
-        };
-        let info = mock_info("creator", &[]);
+    make_offer(
+        deps,
+        env,
+        info.sender,
+        nft_contract,
+        token_id,
+        expires,
+        price,
+    )
+}
 
-        // we can just call .unwrap() to assert this was a success
-        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(0, res.messages.len());
+fn make_offer(
+    deps: DepsMut,
+    env: Env,
+    bidder: Addr,
+    nft_contract: String,
+    token_id: String,
+    expires: Timestamp,
+    price: Price,
+) -> Result<Response, ContractError> {
+    if expires <= env.block.time {
+        return Err(ContractError::InvalidExpiration {});
     }
+    let nft_contract = deps.api.addr_validate(&nft_contract)?;
+    let key = (&nft_contract, token_id.as_str(), &bidder);
 
-    #[test]
-    fn sell_nft() {
-        let mut deps = mock_dependencies();
-        setup(deps.as_mut());
-
-        let sell_msg = SellNft {
-            list_price: coin(1000, "earth"),
-        };
+    // a new offer on the same token replaces the previous one
+    let mut messages = vec![];
+    if let Some(previous) = token_offers().may_load(deps.storage, key)? {
+        messages.push(transfer_price(
+            &previous.price,
+            &bidder,
+            previous.price.amount(),
+        )?);
+    }
 
-        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
-            token_id: "1".into(),
-            sender: "owner".into(),
-            msg: to_binary(&sell_msg).unwrap(),
-        });
-        let info = mock_info("nft-collectibles", &[]);
-        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+    let offer = TokenOffer {
+        nft_contract: nft_contract.clone(),
+        token_id: token_id.clone(),
+        bidder: bidder.clone(),
+        price,
+        expires,
+    };
+    token_offers().save(deps.storage, key, &offer)?;
 
-        assert_eq!(0, res.messages.len());
+    let res = Response::new()
+        .add_attribute("action", "make_offer")
+        .add_attribute("nft_contract", nft_contract)
+        .add_attribute("token_id", token_id)
+        .add_attribute("bidder", bidder)
+        .add_attribute("price", offer.price.to_string())
+        .add_attribute("expires", expires.to_string())
+        .add_messages(messages);
+    Ok(res)
+}
 
-        let msg = QueryMsg::AllOffers {
+pub fn execute_cancel_offer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    nft_contract: String,
+    token_id: String,
+    bidder: Option<String>,
+) -> Result<Response, ContractError> {
+    let nft_contract = deps.api.addr_validate(&nft_contract)?;
+    let bidder = maybe_addr(deps.api, bidder)?.unwrap_or_else(|| info.sender.clone());
+    let key = (&nft_contract, token_id.as_str(), &bidder);
+    let offer = token_offers().load(deps.storage, key)?;
+
+    // anyone can send an expired offer back to its bidder
+    if bidder.ne(&info.sender) && env.block.time < offer.expires {
+        return Err(ContractError::OfferNotExpired {});
+    }
+    token_offers().remove(deps.storage, key)?;
+
+    let res = Response::new()
+        .add_attribute("action", "cancel_offer")
+        .add_attribute("nft_contract", nft_contract)
+        .add_attribute("token_id", token_id)
+        .add_attribute("bidder", bidder.clone())
+        .add_attribute("refund", offer.price.to_string())
+        .add_message(transfer_price(&offer.price, &bidder, offer.price.amount())?);
+    Ok(res)
+}
+
+pub fn execute_accept_offer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    nft_contract: String,
+    token_id: String,
+    bidder: String,
+) -> Result<Response, ContractError> {
+    let nft_contract = deps.api.addr_validate(&nft_contract)?;
+    let msg = Cw721QueryMsg::OwnerOf {
+        token_id: token_id.clone(),
+        include_expired: None,
+    };
+    let owner: OwnerOfResponse = deps.querier.query_wasm_smart(&nft_contract, &msg)?;
+    if owner.owner != info.sender {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    accept_offer(deps, env, info.sender, nft_contract, token_id, bidder)
+}
+
+/// Sells the token to the bidder, the market must hold or be approved to transfer it
+fn accept_offer(
+    mut deps: DepsMut,
+    env: Env,
+    seller: Addr,
+    nft_contract: Addr,
+    token_id: String,
+    bidder: String,
+) -> Result<Response, ContractError> {
+    let bidder = deps.api.addr_validate(&bidder)?;
+    let key = (&nft_contract, token_id.as_str(), &bidder);
+    let offer = token_offers().load(deps.storage, key)?;
+    if env.block.time >= offer.expires {
+        return Err(ContractError::OfferExpired {});
+    }
+    if seller.eq(&bidder) {
+        return Err(ContractError::InvalidBuyer {});
+    }
+    token_offers().remove(deps.storage, key)?;
+
+    let (messages, payout) = settle_sale(
+        deps.branch(),
+        &nft_contract,
+        &token_id,
+        &offer.price,
+        &seller,
+    )?;
+    let transfer = transfer_nft(&nft_contract, &bidder, &token_id)?;
+
+    let res = Response::new()
+        .add_attribute("action", "accept_offer")
+        .add_attribute("nft_contract", nft_contract)
+        .add_attribute("token_id", token_id)
+        .add_attribute("seller", seller)
+        .add_attribute("bidder", bidder)
+        .add_attribute("price", offer.price.to_string())
+        .add_attributes(payout)
+        .add_messages(messages)
+        .add_message(transfer);
+    Ok(res)
+}
+
+pub fn execute_make_collection_bid(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    nft_contract: String,
+    price: Uint128,
+    quantity: u32,
+    expires: Timestamp,
+) -> Result<Response, ContractError> {
+    let paid = match info.funds.as_slice() {
+        [fund] => fund.clone(),
+        _ => return Err(ContractError::InvalidFunds {}),
+    };
+
+    let price = Price::from(coin(price.u128(), paid.denom));
+    validate_escrow(&price, quantity, paid.amount)?;
+
+    make_collection_bid(
+        deps,
+        env,
+        info.sender,
+        nft_contract,
+        price,
+        quantity,
+        expires,
+    )
+}
+
+/// The escrow must cover `quantity` tokens at the bid price
+fn validate_escrow(price: &Price, quantity: u32, paid: Uint128) -> Result<(), ContractError> {
+    let total = price
+        .amount()
+        .checked_mul(quantity.into())
+        .map_err(StdError::from)?;
+    if total.is_zero() || total != paid {
+        return Err(ContractError::InvalidFunds {});
+    }
+    Ok(())
+}
+
+fn make_collection_bid(
+    deps: DepsMut,
+    env: Env,
+    bidder: Addr,
+    nft_contract: String,
+    price: Price,
+    quantity: u32,
+    expires: Timestamp,
+) -> Result<Response, ContractError> {
+    if expires <= env.block.time {
+        return Err(ContractError::InvalidExpiration {});
+    }
+
+    let id = increment_collection_bids(deps.storage)?;
+    let bid = CollectionBid {
+        nft_contract: deps.api.addr_validate(&nft_contract)?,
+        bidder,
+        price,
+        quantity,
+        expires,
+    };
+    collection_bids().save(deps.storage, id, &bid)?;
+
+    let res = Response::new()
+        .add_attribute("action", "make_collection_bid")
+        .add_attribute("bid_id", id.to_string())
+        .add_attribute("nft_contract", bid.nft_contract)
+        .add_attribute("bidder", bid.bidder)
+        .add_attribute("price", bid.price.to_string())
+        .add_attribute("quantity", quantity.to_string())
+        .add_attribute("expires", expires.to_string());
+    Ok(res)
+}
+
+pub fn execute_cancel_collection_bid(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    bid_id: String,
+) -> Result<Response, ContractError> {
+    let id = collection_bid_key(&bid_id)?;
+    let bid = collection_bids().load(deps.storage, id)?;
+
+    // anyone can send an expired bid back to its bidder
+    if bid.bidder.ne(&info.sender) && env.block.time < bid.expires {
+        return Err(ContractError::OfferNotExpired {});
+    }
+    collection_bids().remove(deps.storage, id)?;
+
+    let refund = bid.price.amount() * Uint128::from(bid.quantity);
+    let res = Response::new()
+        .add_attribute("action", "cancel_collection_bid")
+        .add_attribute("bid_id", bid_id)
+        .add_attribute("bidder", bid.bidder.clone())
+        .add_attribute("refund", bid.price.with_amount(refund).to_string())
+        .add_message(transfer_price(&bid.price, &bid.bidder, refund)?);
+    Ok(res)
+}
+
+/// Sells the received token to the collection bid, filling one of its tokens
+pub fn execute_fill_collection_bid(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    wrapper: Cw721ReceiveMsg,
+    bid_id: String,
+) -> Result<Response, ContractError> {
+    let id = collection_bid_key(&bid_id)?;
+    let mut bid = collection_bids().load(deps.storage, id)?;
+    if bid.nft_contract.ne(&info.sender) {
+        return Err(ContractError::InvalidToken {});
+    }
+    if env.block.time >= bid.expires {
+        return Err(ContractError::OfferExpired {});
+    }
+    let seller = deps.api.addr_validate(&wrapper.sender)?;
+    if seller.eq(&bid.bidder) {
+        return Err(ContractError::InvalidBuyer {});
+    }
+
+    bid.quantity -= 1;
+    if bid.quantity == 0 {
+        collection_bids().remove(deps.storage, id)?;
+    } else {
+        collection_bids().save(deps.storage, id, &bid)?;
+    }
+
+    let (messages, payout) = settle_sale(
+        deps.branch(),
+        &bid.nft_contract,
+        &wrapper.token_id,
+        &bid.price,
+        &seller,
+    )?;
+    let transfer = transfer_nft(&bid.nft_contract, &bid.bidder, &wrapper.token_id)?;
+
+    let res = Response::new()
+        .add_attribute("action", "fill_collection_bid")
+        .add_attribute("bid_id", bid_id)
+        .add_attribute("nft_contract", bid.nft_contract)
+        .add_attribute("token_id", wrapper.token_id)
+        .add_attribute("seller", seller)
+        .add_attribute("bidder", bid.bidder)
+        .add_attribute("price", bid.price.to_string())
+        .add_attribute("remaining", bid.quantity.to_string())
+        .add_attributes(payout)
+        .add_messages(messages)
+        .add_message(transfer);
+    Ok(res)
+}
+
+pub fn execute_withdraw_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    amount: Uint128,
+    denom: String,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+
+    if state.owner.ne(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+    if amount.is_zero() {
+        return Err(ContractError::InvalidWithdrawAmount {});
+    }
+
+    let recipient = maybe_addr(deps.api, recipient)?.unwrap_or(state.owner);
+    let accrued = FEES
+        .may_load(deps.storage, &denom)?
+        .ok_or(ContractError::InsufficientFunds {})?;
+    let left = accrued
+        .amount()
+        .checked_sub(amount)
+        .map_err(|_| ContractError::InsufficientFunds {})?;
+    if left.is_zero() {
+        FEES.remove(deps.storage, &denom);
+    } else {
+        FEES.save(deps.storage, &denom, &accrued.with_amount(left))?;
+    }
+
+    let res = Response::new()
+        .add_attribute("action", "withdraw_fees")
+        .add_attribute("recipient", recipient.to_string())
+        .add_attribute("amount", format!("{}{}", amount, denom))
+        .add_message(transfer_price(&accrued, &recipient, amount)?);
+    Ok(res)
+}
+
+pub fn execute_withdraw_all_fees(
+    deps: DepsMut,
+    info: MessageInfo,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    let state = STATE.load(deps.storage)?;
+
+    if state.owner.ne(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    let recipient = maybe_addr(deps.api, recipient)?.unwrap_or(state.owner);
+    let fees = FEES
+        .range(deps.storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    let mut res = Response::new()
+        .add_attribute("action", "withdraw_all_fees")
+        .add_attribute("recipient", recipient.to_string());
+    for (denom, fee) in fees {
+        FEES.remove(deps.storage, &denom);
+        res = res
+            .add_attribute("amount", fee.to_string())
+            .add_message(transfer_price(&fee, &recipient, fee.amount())?);
+    }
+
+    Ok(res)
+}
+
+pub fn execute_change_fee(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    fee: Decimal,
+) -> Result<Response, ContractError> {
+    let mut state = STATE.load(deps.storage)?;
+    if state.owner.ne(&info.sender) {
+        return Err(ContractError::Unauthorized {});
+    }
+
+    validate_fee(fee, state.max_fee)?;
+
+    // sellers get notice before an increase, decreases apply right away
+    let mut res = Response::new()
+        .add_attribute("action", "change_fee")
+        .add_attribute("fee", fee.to_string());
+    if fee > state.fee && state.fee_timelock > 0 {
+        let effective_at = env.block.time.plus_seconds(state.fee_timelock);
+        PENDING_FEE.save(deps.storage, &PendingFee { fee, effective_at })?;
+        res = res.add_attribute("effective_at", effective_at.to_string());
+    } else {
+        state.fee = fee;
+        STATE.save(deps.storage, &state)?;
+        PENDING_FEE.remove(deps.storage);
+    }
+
+    Ok(res)
+}
+
+fn validate_fee(fee: Decimal, max: Decimal) -> Result<(), ContractError> {
+    if fee > max {
+        return Err(ContractError::InvalidFee { max });
+    }
+    Ok(())
+}
+
+/// Returns the fee increase if its timelock is over
+fn matured_fee(storage: &dyn Storage, env: &Env) -> StdResult<Option<Decimal>> {
+    let pending = PENDING_FEE.may_load(storage)?;
+    Ok(pending
+        .filter(|pending| env.block.time >= pending.effective_at)
+        .map(|pending| pending.fee))
+}
+
+fn apply_pending_fee(storage: &mut dyn Storage, env: &Env) -> StdResult<()> {
+    if let Some(fee) = matured_fee(storage, env)? {
+        STATE.update(storage, |mut state| -> StdResult<_> {
+            state.fee = fee;
+            Ok(state)
+        })?;
+        PENDING_FEE.remove(storage);
+    }
+    Ok(())
+}
+
+#[entry_point]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
+    match msg {
+        QueryMsg::GetCount {} => to_binary(&query_count(deps)?),
+        QueryMsg::GetFee {} => to_binary(&query_fee(deps, env)?),
+        QueryMsg::PendingFee {} => to_binary(&query_pending_fee(deps, env)?),
+        QueryMsg::AllOffers {
+            start_after,
+            limit,
+            order,
+        } => to_binary(&query_all(deps, start_after, limit, order)?),
+        QueryMsg::Offer { offering_id } => to_binary(&query_offer(deps, offering_id)?),
+        QueryMsg::OffersBySeller {
+            seller,
+            start_after,
+            limit,
+            order,
+        } => to_binary(&query_offers_by_seller(
+            deps,
+            seller,
+            start_after,
+            limit,
+            order,
+        )?),
+        QueryMsg::OffersByCollection {
+            nft_contract,
+            start_after,
+            limit,
+            order,
+        } => to_binary(&query_offers_by_collection(
+            deps,
+            nft_contract,
+            start_after,
+            limit,
+            order,
+        )?),
+        QueryMsg::OfferByToken {
+            nft_contract,
+            token_id,
+        } => to_binary(&query_offer_by_token(deps, nft_contract, token_id)?),
+        QueryMsg::CurrentPrice { offering_id } => {
+            to_binary(&query_current_price(deps, env, offering_id)?)
+        }
+        QueryMsg::OffersByToken {
+            nft_contract,
+            token_id,
+            start_after,
+            limit,
+        } => to_binary(&query_offers_by_token(
+            deps,
+            nft_contract,
+            token_id,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::OffersByBidder {
+            bidder,
+            start_after,
+            limit,
+        } => to_binary(&query_offers_by_bidder(deps, bidder, start_after, limit)?),
+        QueryMsg::CollectionBid { bid_id } => to_binary(&query_collection_bid(deps, bid_id)?),
+        QueryMsg::TopCollectionBids {
+            nft_contract,
+            denom,
+            start_after,
+            limit,
+        } => to_binary(&query_top_collection_bids(
+            deps,
+            env,
+            nft_contract,
+            denom,
+            start_after,
+            limit,
+        )?),
+        QueryMsg::AccruedFees {} => to_binary(&query_accrued_fees(deps)?),
+        QueryMsg::Auction { auction_id } => to_binary(&query_auction(deps, auction_id)?),
+        QueryMsg::ActiveAuctions { start_after, limit } => {
+            to_binary(&query_active_auctions(deps, env, start_after, limit)?)
+        }
+        QueryMsg::BidHistory {
+            auction_id,
+            start_after,
+            limit,
+        } => to_binary(&query_bid_history(deps, auction_id, start_after, limit)?),
+    }
+}
+
+fn query_count(deps: Deps) -> StdResult<CountResponse> {
+    let state = STATE.load(deps.storage)?;
+    Ok(CountResponse {
+        count: state.num_offerings,
+    })
+}
+
+fn query_fee(deps: Deps, env: Env) -> StdResult<FeeResponse> {
+    let state = STATE.load(deps.storage)?;
+    let fee = matured_fee(deps.storage, &env)?.unwrap_or(state.fee);
+    Ok(FeeResponse {
+        fee,
+        max_fee: state.max_fee,
+        fee_timelock: state.fee_timelock,
+    })
+}
+
+fn query_pending_fee(deps: Deps, env: Env) -> StdResult<PendingFeeResponse> {
+    let pending = PENDING_FEE
+        .may_load(deps.storage)?
+        .filter(|pending| env.block.time < pending.effective_at);
+    Ok(PendingFeeResponse { pending })
+}
+
+fn query_current_price(
+    deps: Deps,
+    env: Env,
+    offering_id: String,
+) -> StdResult<CurrentPriceResponse> {
+    let off = offerings().load(deps.storage, offering_key(&offering_id)?)?;
+    Ok(CurrentPriceResponse {
+        price: off.current_price(env.block.time),
+    })
+}
+
+fn query_accrued_fees(deps: Deps) -> StdResult<AccruedFeesResponse> {
+    let fees: StdResult<Vec<Price>> = FEES
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, fee)| fee))
+        .collect();
+
+    Ok(AccruedFeesResponse { fees: fees? })
+}
+
+fn query_auction(deps: Deps, auction_id: String) -> StdResult<AuctionInfo> {
+    let id = auction_key(&auction_id)?;
+    let auction = AUCTIONS.load(deps.storage, id)?;
+    Ok(map_auction((id, auction)))
+}
+
+fn query_active_auctions(
+    deps: Deps,
+    env: Env,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<AuctionsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = match start_after {
+        Some(id) => Some(Bound::exclusive(auction_key(&id)?)),
+        None => None,
+    };
+
+    // ended auctions are skipped, but only within a bounded scan
+    let mut range = AUCTIONS.range(deps.storage, start, None, Order::Ascending);
+    let mut auctions = vec![];
+    let mut last_scanned = None;
+    for item in range.by_ref().take(MAX_AUCTION_SCAN) {
+        let (id, auction) = item?;
+        last_scanned = Some(id);
+        if env.block.time < auction.end_time {
+            auctions.push(map_auction((id, auction)));
+            if auctions.len() == limit {
+                break;
+            }
+        }
+    }
+
+    // the scan resumes after the last auction read, until none are left
+    let next_start_after = match last_scanned {
+        Some(id) if range.next().is_some() => Some(id.to_string()),
+        _ => None,
+    };
+
+    Ok(AuctionsResponse {
+        auctions,
+        next_start_after,
+    })
+}
+
+fn query_bid_history(
+    deps: Deps,
+    auction_id: String,
+    start_after: Option<u32>,
+    limit: Option<u32>,
+) -> StdResult<BidsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let start = start_after.map(Bound::exclusive);
+
+    let bids: StdResult<Vec<Bid>> = AUCTION_BIDS
+        .prefix(auction_key(&auction_id)?)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, bid)| bid))
+        .collect();
+
+    Ok(BidsResponse { bids: bids? })
+}
+
+fn query_offers_by_token(
+    deps: Deps,
+    nft_contract: String,
+    token_id: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<TokenOffersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let nft_contract = deps.api.addr_validate(&nft_contract)?;
+    let start_after = maybe_addr(deps.api, start_after)?;
+    let start = start_after.as_ref().map(Bound::exclusive);
+
+    let offers: StdResult<Vec<TokenOffer>> = token_offers()
+        .prefix((&nft_contract, &token_id))
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, offer)| offer))
+        .collect();
+
+    Ok(TokenOffersResponse { offers: offers? })
+}
+
+fn query_offers_by_bidder(
+    deps: Deps,
+    bidder: String,
+    start_after: Option<(String, String)>,
+    limit: Option<u32>,
+) -> StdResult<TokenOffersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let bidder = deps.api.addr_validate(&bidder)?;
+    let start = match start_after {
+        Some((nft_contract, token_id)) => {
+            let nft_contract = deps.api.addr_validate(&nft_contract)?;
+            Some(Bound::exclusive((nft_contract, token_id, bidder.clone())))
+        }
+        None => None,
+    };
+
+    let offers: StdResult<Vec<TokenOffer>> = token_offers()
+        .idx
+        .bidder
+        .prefix(bidder)
+        .range(deps.storage, start, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, offer)| offer))
+        .collect();
+
+    Ok(TokenOffersResponse { offers: offers? })
+}
+
+fn query_collection_bid(deps: Deps, bid_id: String) -> StdResult<CollectionBidInfo> {
+    let id = collection_bid_key(&bid_id)?;
+    let bid = collection_bids().load(deps.storage, id)?;
+    Ok(map_collection_bid((id, bid)))
+}
+
+fn query_top_collection_bids(
+    deps: Deps,
+    env: Env,
+    nft_contract: String,
+    denom: String,
+    start_after: Option<(Uint128, String)>,
+    limit: Option<u32>,
+) -> StdResult<CollectionBidsResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let nft_contract = deps.api.addr_validate(&nft_contract)?;
+    // descending order, the cursor is the upper bound
+    let end = match start_after {
+        Some((price, id)) => Some(Bound::exclusive((price.u128(), collection_bid_key(&id)?))),
+        None => None,
+    };
+
+    // expired bids are skipped, but only within a bounded scan
+    let mut range = collection_bids()
+        .idx
+        .price
+        .sub_prefix((nft_contract, denom))
+        .range(deps.storage, None, end, Order::Descending);
+    let mut bids = vec![];
+    let mut last_scanned = None;
+    for item in range.by_ref().take(MAX_COLLECTION_BID_SCAN) {
+        let (id, bid) = item?;
+        last_scanned = Some((bid.price.amount(), id));
+        if env.block.time < bid.expires {
+            bids.push(map_collection_bid((id, bid)));
+            if bids.len() == limit {
+                break;
+            }
+        }
+    }
+
+    let next_start_after = match last_scanned {
+        Some((price, id)) if range.next().is_some() => Some((price, id.to_string())),
+        _ => None,
+    };
+
+    Ok(CollectionBidsResponse {
+        bids,
+        next_start_after,
+    })
+}
+
+fn map_collection_bid((id, bid): (u64, CollectionBid)) -> CollectionBidInfo {
+    CollectionBidInfo {
+        id: id.to_string(),
+        nft_contract: bid.nft_contract,
+        bidder: bid.bidder,
+        price: bid.price,
+        quantity: bid.quantity,
+        expires: bid.expires,
+    }
+}
+
+fn map_auction((id, auction): (u64, Auction)) -> AuctionInfo {
+    AuctionInfo {
+        id: id.to_string(),
+        token_id: auction.token_id,
+        contract: auction.contract,
+        seller: auction.seller,
+        reserve_price: auction.reserve_price,
+        start_time: auction.start_time,
+        end_time: auction.end_time,
+        min_increment: auction.min_increment,
+        extension: auction.extension,
+        highest_bid: auction.highest_bid,
+    }
+}
+
+/// Range of the offering ids after `start_after` in the given order
+type OfferingPage<'a> = (Option<Bound<'a, u64>>, Option<Bound<'a, u64>>, Order);
+
+fn offering_page<'a>(
+    start_after: Option<String>,
+    order: Option<OrderBy>,
+) -> StdResult<OfferingPage<'a>> {
+    let after = match start_after {
+        Some(id) => Some(Bound::exclusive(offering_key(&id)?)),
+        None => None,
+    };
+    Ok(match order.unwrap_or_default() {
+        OrderBy::Ascending => (after, None, Order::Ascending),
+        OrderBy::Descending => (None, after, Order::Descending),
+    })
+}
+
+fn query_all(
+    deps: Deps,
+    start_after: Option<String>,
+    limit: Option<u32>,
+    order: Option<OrderBy>,
+) -> StdResult<OffersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let (min, max, order) = offering_page(start_after, order)?;
+
+    let offers: StdResult<Vec<Offer>> = offerings()
+        .range(deps.storage, min, max, order)
+        .take(limit)
+        .map(|item| item.map(map_offer))
+        .collect();
+
+    Ok(OffersResponse { offers: offers? })
+}
+
+fn query_offer(deps: Deps, offering_id: String) -> StdResult<Offer> {
+    let id = offering_key(&offering_id)?;
+    let off = offerings().load(deps.storage, id)?;
+    Ok(map_offer((id, off)))
+}
+
+fn query_offers_by_seller(
+    deps: Deps,
+    seller: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+    order: Option<OrderBy>,
+) -> StdResult<OffersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let seller = deps.api.addr_validate(&seller)?;
+    let (min, max, order) = offering_page(start_after, order)?;
+
+    let offers: StdResult<Vec<Offer>> = offerings()
+        .idx
+        .seller
+        .prefix(seller)
+        .range(deps.storage, min, max, order)
+        .take(limit)
+        .map(|item| item.map(map_offer))
+        .collect();
+
+    Ok(OffersResponse { offers: offers? })
+}
+
+fn query_offers_by_collection(
+    deps: Deps,
+    nft_contract: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+    order: Option<OrderBy>,
+) -> StdResult<OffersResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LIMIT).min(MAX_LIMIT) as usize;
+    let nft_contract = deps.api.addr_validate(&nft_contract)?;
+    let (min, max, order) = offering_page(start_after, order)?;
+
+    let offers: StdResult<Vec<Offer>> = offerings()
+        .idx
+        .contract
+        .prefix(nft_contract)
+        .range(deps.storage, min, max, order)
+        .take(limit)
+        .map(|item| item.map(map_offer))
+        .collect();
+
+    Ok(OffersResponse { offers: offers? })
+}
+
+fn query_offer_by_token(
+    deps: Deps,
+    nft_contract: String,
+    token_id: String,
+) -> StdResult<OfferResponse> {
+    let nft_contract = deps.api.addr_validate(&nft_contract)?;
+    let offer = offerings()
+        .idx
+        .token
+        .item(deps.storage, (nft_contract, token_id))?
+        .map(|(id, off)| -> StdResult<_> {
+            let id = id
+                .try_into()
+                .map_err(|_| StdError::generic_err("Invalid offering key"))?;
+            Ok(map_offer((u64::from_be_bytes(id), off)))
+        })
+        .transpose()?;
+
+    Ok(OfferResponse { offer })
+}
+
+fn map_offer((k, v): (u64, Offering)) -> Offer {
+    Offer {
+        id: k.to_string(),
+        token_id: v.token_id,
+        contract: v.contract,
+        seller: v.seller,
+        list_price: v.list_price,
+        pricing: v.pricing,
+        expires: v.expires,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::msg::{AcceptOfferNft, FillCollectionBid};
+    use cosmwasm_std::testing::{
+        mock_dependencies, mock_dependencies_with_balance, mock_env, mock_info,
+    };
+    use cosmwasm_std::{
+        coins, ContractResult, Decimal, SubMsg, SystemError, SystemResult, WasmQuery,
+    };
+
+    fn setup(deps: DepsMut) {
+        let msg = InstantiateMsg {
+            fee: Decimal::percent(2),
+            max_fee: Some(Decimal::percent(10)),
+            fee_timelock: None,
+        };
+        let info = mock_info("creator", &[]);
+
+        // we can just call .unwrap() to assert this was a success
+        instantiate(deps, mock_env(), info, msg).unwrap();
+    }
+
+    #[test]
+    fn proper_initialization() {
+        let mut deps = mock_dependencies();
+
+        let msg = InstantiateMsg {
+            fee: Decimal::percent(2),
+            max_fee: Some(Decimal::percent(10)),
+            fee_timelock: None,
+        };
+        let info = mock_info("creator", &[]);
+
+        // we can just call .unwrap() to assert this was a success
+        let res = instantiate(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // the fee cap and timelock are optional
+        let mut deps = mock_dependencies();
+        let msg = InstantiateMsg {
+            fee: Decimal::percent(2),
+            max_fee: None,
+            fee_timelock: None,
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+        let res = query_fee(deps.as_ref(), mock_env()).unwrap();
+        assert_eq!(Decimal::one(), res.max_fee);
+        assert_eq!(0, res.fee_timelock);
+    }
+
+    #[test]
+    fn sell_nft() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        let sell_msg = SellNft {
+            list_price: coin(1000, "earth").into(),
+            pricing: None,
+            expires: None,
+        };
+
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+            token_id: "1".into(),
+            sender: "owner".into(),
+            msg: to_binary(&sell_msg).unwrap(),
+        });
+        let info = mock_info("nft-collectibles", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        assert_eq!(0, res.messages.len());
+
+        let msg = QueryMsg::AllOffers {
+            start_after: None,
+            limit: None,
+            order: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: OffersResponse = from_binary(&res).unwrap();
+
+        assert_eq!("1", value.offers.first().unwrap().token_id);
+    }
+
+    #[test]
+    fn buy_nft() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        let sell_msg = SellNft {
+            list_price: coin(1000, "earth").into(),
+            pricing: None,
+            expires: None,
+        };
+
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+            token_id: "1".into(),
+            sender: "owner".into(),
+            msg: to_binary(&sell_msg).unwrap(),
+        });
+        let info = mock_info("nft-collectibles", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = ExecuteMsg::Buy {
+            offering_id: "1".into(),
+        };
+        let info = mock_info("owner", &coins(1000, "earth"));
+        let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+        match res {
+            Err(ContractError::InvalidBuyer {}) => {}
+            _ => panic!("Must return InvalidBuyer error"),
+        }
+
+        let info = mock_info("anyone", &coins(400, "earth"));
+        let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+        match res {
+            Err(ContractError::InsufficientFunds {}) => {}
+            _ => panic!("Must return InsufficientFunds error"),
+        }
+
+        let info = mock_info("anyone", &coins(1000, "earth"));
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        assert_eq!(2, res.messages.len());
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "owner".into(),
+                amount: coins(980, "earth")
+            }))
+        );
+    }
+
+    #[test]
+    fn withdraw_fees() {
+        let mut deps = mock_dependencies_with_balance(&coins(1000, "earth"));
+        setup(deps.as_mut());
+
+        // nothing accrued yet
+        let msg = ExecuteMsg::WithdrawFees {
+            amount: 10u32.into(),
+            denom: "earth".into(),
+            recipient: None,
+        };
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg);
+        match res {
+            Err(ContractError::InsufficientFunds {}) => {}
+            _ => panic!("Must return InsufficientFunds error"),
+        }
+
+        // sell and buy to accrue a 2% fee
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+            token_id: "1".into(),
+            sender: "owner".into(),
+            msg: to_binary(&SellNft {
+                list_price: coin(1000, "earth").into(),
+                pricing: None,
+                expires: None,
+            })
+            .unwrap(),
+        });
+        let info = mock_info("nft-collectibles", &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        let msg = ExecuteMsg::Buy {
+            offering_id: "1".into(),
+        };
+        let info = mock_info("anyone", &coins(1000, "earth"));
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::AccruedFees {}).unwrap();
+        let value: AccruedFeesResponse = from_binary(&res).unwrap();
+        assert_eq!(vec![Price::from(coin(20, "earth"))], value.fees);
+
+        let msg = ExecuteMsg::WithdrawFees {
+            amount: 15u32.into(),
+            denom: "earth".into(),
+            recipient: Some("treasury".into()),
+        };
+        let info = mock_info("anyone", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return Unauthorized error"),
+        }
+
+        // zero amount
+        let info = mock_info("creator", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::WithdrawFees {
+                amount: Uint128::zero(),
+                denom: "earth".into(),
+                recipient: None,
+            },
+        );
+        match res {
+            Err(ContractError::InvalidWithdrawAmount {}) => {}
+            _ => panic!("Must return InvalidWithdrawAmount error"),
+        }
+
+        // more than accrued
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            ExecuteMsg::WithdrawFees {
+                amount: 21u32.into(),
+                denom: "earth".into(),
+                recipient: None,
+            },
+        );
+        match res {
+            Err(ContractError::InsufficientFunds {}) => {}
+            _ => panic!("Must return InsufficientFunds error"),
+        }
+
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        assert_eq!(1, res.messages.len());
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "treasury".into(),
+                amount: coins(15, "earth")
+            }))
+        );
+
+        let msg = ExecuteMsg::WithdrawAllFees { recipient: None };
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(1, res.messages.len());
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
+                to_address: "creator".into(),
+                amount: coins(5, "earth")
+            }))
+        );
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::AccruedFees {}).unwrap();
+        let value: AccruedFeesResponse = from_binary(&res).unwrap();
+        assert!(value.fees.is_empty());
+    }
+
+    #[test]
+    fn change_fee() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        let msg = ExecuteMsg::ChangeFee {
+            fee: Decimal::percent(3),
+        };
+        let info = mock_info("anyone", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return Unauthorized error"),
+        }
+
+        let info = mock_info("creator", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        let msg = QueryMsg::GetFee {};
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: FeeResponse = from_binary(&res).unwrap();
+        assert_eq!(Decimal::percent(3), value.fee);
+    }
+
+    #[test]
+    fn buy_nft_cw20() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        let sell_msg = SellNft {
+            list_price: Price::Cw20(Cw20Coin {
+                address: "token".into(),
+                amount: 1000u128.into(),
+            }),
+            pricing: None,
+            expires: None,
+        };
+
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+            token_id: "1".into(),
+            sender: "owner".into(),
+            msg: to_binary(&sell_msg).unwrap(),
+        });
+        let info = mock_info("nft-collectibles", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        // native funds cannot buy a cw20 listing
+        let msg = ExecuteMsg::Buy {
+            offering_id: "1".into(),
+        };
+        let info = mock_info("anyone", &coins(1000, "earth"));
+        let res = execute(deps.as_mut(), mock_env(), info, msg);
+        match res {
+            Err(ContractError::InvalidToken {}) => {}
+            _ => panic!("Must return InvalidToken error"),
+        }
+
+        let buy = |amount: u128| {
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: "anyone".into(),
+                amount: amount.into(),
+                msg: to_binary(&ReceiveMsg::Buy {
+                    offering_id: "1".into(),
+                })
+                .unwrap(),
+            })
+        };
+
+        let info = mock_info("other-token", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, buy(1000));
+        match res {
+            Err(ContractError::InvalidToken {}) => {}
+            _ => panic!("Must return InvalidToken error"),
+        }
+
+        let info = mock_info("token", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), buy(400));
+        match res {
+            Err(ContractError::InsufficientFunds {}) => {}
+            _ => panic!("Must return InsufficientFunds error"),
+        }
+
+        let res = execute(deps.as_mut(), mock_env(), info, buy(1000)).unwrap();
+        assert_eq!(2, res.messages.len());
+        assert_eq!(
+            res.messages[0],
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "token".into(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "owner".into(),
+                    amount: 980u128.into(),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+        assert_eq!(
+            res.messages[1],
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "nft-collectibles".into(),
+                msg: to_binary(&Cw721ExecuteMsg::TransferNft {
+                    recipient: "anyone".into(),
+                    token_id: "1".into(),
+                })
+                .unwrap(),
+                funds: vec![],
+            })
+        );
+
+        // a native listing whose denom equals the token address is not a cw20 listing
+        let sell_msg = SellNft {
+            list_price: Price::Native(coin(1000, "token")),
+            pricing: None,
+            expires: None,
+        };
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+            token_id: "2".into(),
+            sender: "owner".into(),
+            msg: to_binary(&sell_msg).unwrap(),
+        });
+        let info = mock_info("nft-collectibles", &[]);
+        let _res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "anyone".into(),
+            amount: 1000u128.into(),
+            msg: to_binary(&ReceiveMsg::Buy {
+                offering_id: "2".into(),
+            })
+            .unwrap(),
+        });
+        let info = mock_info("token", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg);
+        match res {
+            Err(ContractError::InvalidToken {}) => {}
+            _ => panic!("Must return InvalidToken error"),
+        }
+    }
+
+    #[test]
+    fn buy_nft_refunds() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        let sell = |deps: DepsMut, price: Price| {
+            let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+                token_id: "1".into(),
+                sender: "owner".into(),
+                msg: to_binary(&SellNft {
+                    list_price: price,
+                    pricing: None,
+                    expires: None,
+                })
+                .unwrap(),
+            });
+            let info = mock_info("nft-collectibles", &[]);
+            execute(deps, mock_env(), info, msg).unwrap();
+        };
+        let seller_payout = SubMsg::new(BankMsg::Send {
+            to_address: "owner".into(),
+            amount: coins(980, "earth"),
+        });
+
+        // exact payment, no refund
+        sell(deps.as_mut(), coin(1000, "earth").into());
+        let msg = ExecuteMsg::Buy {
+            offering_id: "1".into(),
+        };
+        let info = mock_info("anyone", &coins(1000, "earth"));
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(2, res.messages.len());
+        assert_eq!(seller_payout, res.messages[0]);
+
+        // overpayment, seller payout is computed from the list price
+        sell(deps.as_mut(), coin(1000, "earth").into());
+        let msg = ExecuteMsg::Buy {
+            offering_id: "2".into(),
+        };
+        let info = mock_info("anyone", &coins(1200, "earth"));
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(3, res.messages.len());
+        assert_eq!(seller_payout, res.messages[0]);
+        assert_eq!(
+            SubMsg::new(BankMsg::Send {
+                to_address: "anyone".into(),
+                amount: coins(200, "earth"),
+            }),
+            res.messages[2]
+        );
+
+        // unrelated denoms are refunded
+        sell(deps.as_mut(), coin(1000, "earth").into());
+        let msg = ExecuteMsg::Buy {
+            offering_id: "3".into(),
+        };
+        let info = mock_info("anyone", &[coin(1000, "earth"), coin(5, "moon")]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(3, res.messages.len());
+        assert_eq!(seller_payout, res.messages[0]);
+        assert_eq!(
+            SubMsg::new(BankMsg::Send {
+                to_address: "anyone".into(),
+                amount: coins(5, "moon"),
+            }),
+            res.messages[2]
+        );
+
+        // overpayment and unrelated denoms in a single refund
+        sell(deps.as_mut(), coin(1000, "earth").into());
+        let msg = ExecuteMsg::Buy {
+            offering_id: "4".into(),
+        };
+        let info = mock_info("anyone", &[coin(1100, "earth"), coin(5, "moon")]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(3, res.messages.len());
+        assert_eq!(
+            SubMsg::new(BankMsg::Send {
+                to_address: "anyone".into(),
+                amount: vec![coin(100, "earth"), coin(5, "moon")],
+            }),
+            res.messages[2]
+        );
+        assert!(res.attributes.contains(&attr("refund", "100earth,5moon")));
+
+        // cw20 overpayment
+        sell(
+            deps.as_mut(),
+            Price::Cw20(Cw20Coin {
+                address: "token".into(),
+                amount: 1000u128.into(),
+            }),
+        );
+        let msg = ExecuteMsg::Receive(Cw20ReceiveMsg {
+            sender: "anyone".into(),
+            amount: 1300u128.into(),
+            msg: to_binary(&ReceiveMsg::Buy {
+                offering_id: "5".into(),
+            })
+            .unwrap(),
+        });
+        let res = execute(deps.as_mut(), mock_env(), mock_info("token", &[]), msg).unwrap();
+        assert_eq!(3, res.messages.len());
+        assert_eq!(
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "token".into(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "owner".into(),
+                    amount: 980u128.into(),
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+            res.messages[0]
+        );
+        assert_eq!(
+            SubMsg::new(WasmMsg::Execute {
+                contract_addr: "token".into(),
+                msg: to_binary(&Cw20ExecuteMsg::Transfer {
+                    recipient: "anyone".into(),
+                    amount: 300u128.into(),
+                })
+                .unwrap(),
+                funds: vec![],
+            }),
+            res.messages[2]
+        );
+    }
+
+    #[test]
+    fn fee_cap_and_timelock() {
+        let mut deps = mock_dependencies();
+
+        for (fee, max_fee) in [
+            (Decimal::percent(20), Decimal::percent(10)),
+            (Decimal::percent(2), Decimal::percent(110)),
+        ] {
+            let msg = InstantiateMsg {
+                fee,
+                max_fee: Some(max_fee),
+                fee_timelock: Some(100),
+            };
+            let res = instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg);
+            match res {
+                Err(ContractError::InvalidFee { .. }) => {}
+                _ => panic!("Must return InvalidFee error"),
+            }
+        }
+
+        let msg = InstantiateMsg {
+            fee: Decimal::percent(2),
+            max_fee: Some(Decimal::percent(10)),
+            fee_timelock: Some(100),
+        };
+        instantiate(deps.as_mut(), mock_env(), mock_info("creator", &[]), msg).unwrap();
+
+        // above the cap
+        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::ChangeFee {
+            fee: Decimal::percent(11),
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        match res {
+            Err(ContractError::InvalidFee { max }) => assert_eq!(Decimal::percent(10), max),
+            _ => panic!("Must return InvalidFee error"),
+        }
+
+        // increase waits for the timelock
+        let msg = ExecuteMsg::ChangeFee {
+            fee: Decimal::percent(5),
+        };
+        execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::GetFee {}).unwrap();
+        let value: FeeResponse = from_binary(&res).unwrap();
+        assert_eq!(Decimal::percent(2), value.fee);
+
+        let res = query(deps.as_ref(), mock_env(), QueryMsg::PendingFee {}).unwrap();
+        let value: PendingFeeResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            Some(PendingFee {
+                fee: Decimal::percent(5),
+                effective_at: mock_env().block.time.plus_seconds(100),
+            }),
+            value.pending
+        );
+
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(100);
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::GetFee {}).unwrap();
+        let value: FeeResponse = from_binary(&res).unwrap();
+        assert_eq!(Decimal::percent(5), value.fee);
+        let res = query(deps.as_ref(), env.clone(), QueryMsg::PendingFee {}).unwrap();
+        let value: PendingFeeResponse = from_binary(&res).unwrap();
+        assert_eq!(None, value.pending);
+
+        // decrease applies right away
+        let msg = ExecuteMsg::ChangeFee {
+            fee: Decimal::percent(1),
+        };
+        execute(deps.as_mut(), env.clone(), info, msg).unwrap();
+        let res = query(deps.as_ref(), env, QueryMsg::GetFee {}).unwrap();
+        let value: FeeResponse = from_binary(&res).unwrap();
+        assert_eq!(Decimal::percent(1), value.fee);
+    }
+
+    #[test]
+    fn buy_nft_royalties() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { contract_addr, msg } if contract_addr == "nft-collectibles" => {
+                let msg: Cw2981QueryMsg = from_binary(msg).unwrap();
+                let Cw2981QueryMsg::Extension {
+                    msg: Cw2981Extension::RoyaltyInfo { sale_price, .. },
+                } = msg;
+                let res = RoyaltiesInfoResponse {
+                    address: "artist".into(),
+                    royalty_amount: sale_price * Decimal::percent(10),
+                };
+                SystemResult::Ok(ContractResult::Ok(to_binary(&res).unwrap()))
+            }
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "not cw2981".into(),
+            }),
+        });
+
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+            token_id: "1".into(),
+            sender: "owner".into(),
+            msg: to_binary(&ReceiveNftMsg::Sell(SellNft {
+                list_price: coin(1000, "earth").into(),
+                pricing: None,
+                expires: None,
+            }))
+            .unwrap(),
+        });
+        let info = mock_info("nft-collectibles", &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = ExecuteMsg::Buy {
+            offering_id: "1".into(),
+        };
+        let info = mock_info("anyone", &coins(1000, "earth"));
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(3, res.messages.len());
+        assert_eq!(
+            SubMsg::new(BankMsg::Send {
+                to_address: "owner".into(),
+                amount: coins(880, "earth"),
+            }),
+            res.messages[0]
+        );
+        assert_eq!(
+            SubMsg::new(BankMsg::Send {
+                to_address: "artist".into(),
+                amount: coins(100, "earth"),
+            }),
+            res.messages[1]
+        );
+        for attribute in [
+            attr("seller_amount", "880earth"),
+            attr("fee_amount", "20earth"),
+            attr("royalty_recipient", "artist"),
+            attr("royalty_amount", "100earth"),
+        ] {
+            assert!(res.attributes.contains(&attribute));
+        }
+
+        // contracts without royalties pay the seller only
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+            token_id: "1".into(),
+            sender: "owner".into(),
+            msg: to_binary(&SellNft {
+                list_price: coin(1000, "earth").into(),
+                pricing: None,
+                expires: None,
+            })
+            .unwrap(),
+        });
+        let info = mock_info("other-collectibles", &[]);
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let msg = ExecuteMsg::Buy {
+            offering_id: "2".into(),
+        };
+        let info = mock_info("anyone", &coins(1000, "earth"));
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(2, res.messages.len());
+        assert_eq!(
+            SubMsg::new(BankMsg::Send {
+                to_address: "owner".into(),
+                amount: coins(980, "earth"),
+            }),
+            res.messages[0]
+        );
+    }
+
+    #[test]
+    fn english_auction() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        let now = mock_env().block.time;
+        let env_at = |seconds: u64| {
+            let mut env = mock_env();
+            env.block.time = now.plus_seconds(seconds);
+            env
+        };
+        let auction = |end: u64, min_increment: u128| {
+            ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+                token_id: "1".into(),
+                sender: "owner".into(),
+                msg: to_binary(&ReceiveNftMsg::Auction(AuctionNft {
+                    reserve_price: coin(100, "earth").into(),
+                    start_time: now,
+                    end_time: now.plus_seconds(end),
+                    min_increment: min_increment.into(),
+                    extension: Some(300),
+                }))
+                .unwrap(),
+            })
+        };
+        let bid = |auction_id: &str| ExecuteMsg::PlaceBid {
+            auction_id: auction_id.into(),
+        };
+
+        let info = mock_info("nft-collectibles", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), auction(0, 10));
+        match res {
+            Err(ContractError::InvalidAuction {}) => {}
+            _ => panic!("Must return InvalidAuction error"),
+        }
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), auction(1000, 0));
+        match res {
+            Err(ContractError::InvalidIncrement {}) => {}
+            _ => panic!("Must return InvalidIncrement error"),
+        }
+        execute(deps.as_mut(), mock_env(), info, auction(1000, 10)).unwrap();
+
+        // seller cannot bid
+        let info = mock_info("owner", &coins(100, "earth"));
+        let res = execute(deps.as_mut(), env_at(10), info, bid("1"));
+        match res {
+            Err(ContractError::InvalidBuyer {}) => {}
+            _ => panic!("Must return InvalidBuyer error"),
+        }
+
+        // below the reserve price
+        let info = mock_info("alice", &coins(50, "earth"));
+        let res = execute(deps.as_mut(), env_at(10), info, bid("1"));
+        match res {
+            Err(ContractError::BidTooLow { min }) => assert_eq!(Uint128::new(100), min),
+            _ => panic!("Must return BidTooLow error"),
+        }
+
+        let info = mock_info("alice", &coins(100, "earth"));
+        let res = execute(deps.as_mut(), env_at(10), info, bid("1")).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // below the min increment
+        let info = mock_info("bob", &coins(105, "earth"));
+        let res = execute(deps.as_mut(), env_at(800), info, bid("1"));
+        match res {
+            Err(ContractError::BidTooLow { min }) => assert_eq!(Uint128::new(110), min),
+            _ => panic!("Must return BidTooLow error"),
+        }
+
+        // outbid refunds alice and extends the end time
+        let info = mock_info("bob", &coins(120, "earth"));
+        let res = execute(deps.as_mut(), env_at(800), info, bid("1")).unwrap();
+        assert_eq!(
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "alice".into(),
+                amount: coins(100, "earth"),
+            })],
+            res.messages
+        );
+
+        let msg = QueryMsg::Auction {
+            auction_id: "1".into(),
+        };
+        let res = query(deps.as_ref(), env_at(800), msg).unwrap();
+        let value: AuctionInfo = from_binary(&res).unwrap();
+        assert_eq!(now.plus_seconds(1100), value.end_time);
+        assert_eq!(Uint128::new(120), value.highest_bid.unwrap().amount);
+
+        let msg = QueryMsg::BidHistory {
+            auction_id: "1".into(),
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), env_at(800), msg).unwrap();
+        let value: BidsResponse = from_binary(&res).unwrap();
+        let bidders: Vec<_> = value.bids.iter().map(|b| b.bidder.as_str()).collect();
+        assert_eq!(vec!["alice", "bob"], bidders);
+
+        let msg = QueryMsg::ActiveAuctions {
             start_after: None,
             limit: None,
         };
-        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let value: OffersResponse = from_binary(&res).unwrap();
+        let res = query(deps.as_ref(), env_at(1050), msg.clone()).unwrap();
+        let value: AuctionsResponse = from_binary(&res).unwrap();
+        assert_eq!(1, value.auctions.len());
+        let res = query(deps.as_ref(), env_at(1100), msg).unwrap();
+        let value: AuctionsResponse = from_binary(&res).unwrap();
+        assert!(value.auctions.is_empty());
+
+        let msg = ExecuteMsg::SettleAuction {
+            auction_id: "1".into(),
+        };
+        let info = mock_info("anyone", &[]);
+        let res = execute(deps.as_mut(), env_at(1050), info.clone(), msg.clone());
+        match res {
+            Err(ContractError::AuctionNotEnded {}) => {}
+            _ => panic!("Must return AuctionNotEnded error"),
+        }
+
+        let late = mock_info("alice", &coins(200, "earth"));
+        let res = execute(deps.as_mut(), env_at(1100), late, bid("1"));
+        match res {
+            Err(ContractError::AuctionNotActive {}) => {}
+            _ => panic!("Must return AuctionNotActive error"),
+        }
+
+        // winner gets the NFT, seller the proceeds minus the fee
+        let res = execute(deps.as_mut(), env_at(1100), info.clone(), msg).unwrap();
+        assert_eq!(
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "owner".into(),
+                    amount: coins(117, "earth"),
+                }),
+                SubMsg::new(
+                    transfer_nft(
+                        &Addr::unchecked("nft-collectibles"),
+                        &Addr::unchecked("bob"),
+                        "1"
+                    )
+                    .unwrap()
+                ),
+            ],
+            res.messages
+        );
+
+        // without bids the NFT goes back to the seller
+        let nft = mock_info("nft-collectibles", &[]);
+        execute(deps.as_mut(), mock_env(), nft, auction(1000, 10)).unwrap();
+        let msg = ExecuteMsg::SettleAuction {
+            auction_id: "2".into(),
+        };
+        let res = execute(deps.as_mut(), env_at(1000), info, msg).unwrap();
+        assert_eq!(
+            vec![SubMsg::new(
+                transfer_nft(
+                    &Addr::unchecked("nft-collectibles"),
+                    &Addr::unchecked("owner"),
+                    "1"
+                )
+                .unwrap()
+            )],
+            res.messages
+        );
+    }
+
+    #[test]
+    fn active_auctions_cursor() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        let now = mock_env().block.time;
+        let info = mock_info("nft-collectibles", &[]);
+        for (token_id, end) in [("1", 100), ("2", 1000), ("3", 100), ("4", 1000)] {
+            let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+                token_id: token_id.into(),
+                sender: "owner".into(),
+                msg: to_binary(&ReceiveNftMsg::Auction(AuctionNft {
+                    reserve_price: coin(100, "earth").into(),
+                    start_time: now,
+                    end_time: now.plus_seconds(end),
+                    min_increment: 10u128.into(),
+                    extension: None,
+                }))
+                .unwrap(),
+            });
+            execute(deps.as_mut(), mock_env(), info.clone(), msg).unwrap();
+        }
+
+        // ended auctions are skipped, the cursor points past them
+        let mut env = mock_env();
+        env.block.time = now.plus_seconds(500);
+        let page = |start_after: Option<String>| {
+            let msg = QueryMsg::ActiveAuctions {
+                start_after,
+                limit: Some(1),
+            };
+            let res = query(deps.as_ref(), env.clone(), msg).unwrap();
+            from_binary::<AuctionsResponse>(&res).unwrap()
+        };
+        let value = page(None);
+        assert_eq!(
+            vec!["2"],
+            value
+                .auctions
+                .iter()
+                .map(|a| a.id.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(Some("2".to_string()), value.next_start_after);
+        let value = page(value.next_start_after);
+        assert_eq!(
+            vec!["4"],
+            value
+                .auctions
+                .iter()
+                .map(|a| a.id.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!(None, value.next_start_after);
+    }
+
+    #[test]
+    fn cw20_auction_bid() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        let now = mock_env().block.time;
+        let auction = |token_id: &str, reserve_price: Price| {
+            ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+                token_id: token_id.into(),
+                sender: "owner".into(),
+                msg: to_binary(&ReceiveNftMsg::Auction(AuctionNft {
+                    reserve_price,
+                    start_time: now,
+                    end_time: now.plus_seconds(1000),
+                    min_increment: 10u128.into(),
+                    extension: None,
+                }))
+                .unwrap(),
+            })
+        };
+        let info = mock_info("nft-collectibles", &[]);
+        let cw20_price = Price::Cw20(Cw20Coin {
+            address: "token".into(),
+            amount: 100u128.into(),
+        });
+        execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            auction("1", cw20_price),
+        )
+        .unwrap();
+        let native_price = coin(100, "token").into();
+        execute(deps.as_mut(), mock_env(), info, auction("2", native_price)).unwrap();
+
+        let bid = |auction_id: &str| {
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: "alice".into(),
+                amount: 100u128.into(),
+                msg: to_binary(&ReceiveMsg::PlaceBid {
+                    auction_id: auction_id.into(),
+                })
+                .unwrap(),
+            })
+        };
+
+        // another token
+        let info = mock_info("other-token", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, bid("1"));
+        match res {
+            Err(ContractError::InvalidToken {}) => {}
+            _ => panic!("Must return InvalidToken error"),
+        }
+
+        // a native reserve price whose denom equals the token address
+        let info = mock_info("token", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), bid("2"));
+        match res {
+            Err(ContractError::InvalidToken {}) => {}
+            _ => panic!("Must return InvalidToken error"),
+        }
+
+        let res = execute(deps.as_mut(), mock_env(), info, bid("1")).unwrap();
+        assert_eq!(0, res.messages.len());
+    }
+
+    #[test]
+    fn dutch_auction() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        let now = mock_env().block.time;
+        let env_at = |seconds: u64| {
+            let mut env = mock_env();
+            env.block.time = now.plus_seconds(seconds);
+            env
+        };
+        let sell = |floor: u128| {
+            ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+                token_id: "1".into(),
+                sender: "owner".into(),
+                msg: to_binary(&SellNft {
+                    list_price: coin(1000, "earth").into(),
+                    pricing: Some(Pricing::Dutch {
+                        floor_price: floor.into(),
+                        start_time: now,
+                        end_time: now.plus_seconds(800),
+                    }),
+                    expires: None,
+                })
+                .unwrap(),
+            })
+        };
+
+        let info = mock_info("nft-collectibles", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), sell(2000));
+        match res {
+            Err(ContractError::InvalidPricing {}) => {}
+            _ => panic!("Must return InvalidPricing error"),
+        }
+        execute(deps.as_mut(), mock_env(), info, sell(200)).unwrap();
+
+        let current_price = |deps: Deps, seconds: u64| {
+            let msg = QueryMsg::CurrentPrice {
+                offering_id: "1".into(),
+            };
+            let res = query(deps, env_at(seconds), msg).unwrap();
+            from_binary::<CurrentPriceResponse>(&res).unwrap().price
+        };
+        assert_eq!(
+            Price::from(coin(1000, "earth")),
+            current_price(deps.as_ref(), 0)
+        );
+        assert_eq!(
+            Price::from(coin(600, "earth")),
+            current_price(deps.as_ref(), 400)
+        );
+        assert_eq!(
+            Price::from(coin(200, "earth")),
+            current_price(deps.as_ref(), 1000)
+        );
+
+        // the price is computed at purchase
+        let msg = ExecuteMsg::Buy {
+            offering_id: "1".into(),
+        };
+        let info = mock_info("anyone", &coins(599, "earth"));
+        let res = execute(deps.as_mut(), env_at(400), info, msg.clone());
+        match res {
+            Err(ContractError::InsufficientFunds {}) => {}
+            _ => panic!("Must return InsufficientFunds error"),
+        }
 
-        assert_eq!("1", value.offers.first().unwrap().token_id);
+        let info = mock_info("anyone", &coins(600, "earth"));
+        let res = execute(deps.as_mut(), env_at(400), info, msg).unwrap();
+        assert_eq!(
+            SubMsg::new(BankMsg::Send {
+                to_address: "owner".into(),
+                amount: coins(588, "earth"),
+            }),
+            res.messages[0]
+        );
+        assert!(res.attributes.contains(&attr("paid_price", "600earth")));
     }
 
     #[test]
-    fn buy_nft() {
+    fn token_offers() {
         let mut deps = mock_dependencies();
         setup(deps.as_mut());
+        deps.querier.update_wasm(|query| match query {
+            WasmQuery::Smart { contract_addr, msg } if contract_addr == "nft-collectibles" => {
+                match from_binary(msg) {
+                    Ok(Cw721QueryMsg::OwnerOf { .. }) => {
+                        let res = OwnerOfResponse {
+                            owner: "owner".into(),
+                        };
+                        SystemResult::Ok(ContractResult::Ok(to_binary(&res).unwrap()))
+                    }
+                    Err(_) => SystemResult::Err(SystemError::UnsupportedRequest {
+                        kind: "not cw2981".into(),
+                    }),
+                }
+            }
+            _ => SystemResult::Err(SystemError::UnsupportedRequest {
+                kind: "unknown contract".into(),
+            }),
+        });
 
-        let sell_msg = SellNft {
-            list_price: coin(1000, "earth"),
+        let now = mock_env().block.time;
+        let mut expired = mock_env();
+        expired.block.time = now.plus_seconds(100);
+        let offer = |token_id: &str| ExecuteMsg::MakeOffer {
+            nft_contract: "nft-collectibles".into(),
+            token_id: token_id.into(),
+            expires: now.plus_seconds(100),
+        };
+
+        let info = mock_info("bidder", &[coin(100, "earth"), coin(100, "moon")]);
+        let res = execute(deps.as_mut(), mock_env(), info, offer("1"));
+        match res {
+            Err(ContractError::InvalidFunds {}) => {}
+            _ => panic!("Must return InvalidFunds error"),
+        }
+        let info = mock_info("bidder", &coins(100, "earth"));
+        let msg = ExecuteMsg::MakeOffer {
+            nft_contract: "nft-collectibles".into(),
+            token_id: "1".into(),
+            expires: now,
+        };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg);
+        match res {
+            Err(ContractError::InvalidExpiration {}) => {}
+            _ => panic!("Must return InvalidExpiration error"),
+        }
+        let res = execute(deps.as_mut(), mock_env(), info, offer("1")).unwrap();
+        assert_eq!(0, res.messages.len());
+
+        // a higher offer refunds the previous one
+        let info = mock_info("bidder", &coins(500, "earth"));
+        let res = execute(deps.as_mut(), mock_env(), info, offer("1")).unwrap();
+        assert_eq!(
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "bidder".into(),
+                amount: coins(100, "earth"),
+            })],
+            res.messages
+        );
+        let info = mock_info("other", &coins(300, "earth"));
+        execute(deps.as_mut(), mock_env(), info, offer("1")).unwrap();
+        let info = mock_info("other", &coins(200, "earth"));
+        execute(deps.as_mut(), mock_env(), info, offer("2")).unwrap();
+
+        let msg = QueryMsg::OffersByToken {
+            nft_contract: "nft-collectibles".into(),
+            token_id: "1".into(),
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: TokenOffersResponse = from_binary(&res).unwrap();
+        let bidders: Vec<_> = value.offers.iter().map(|o| o.bidder.as_str()).collect();
+        assert_eq!(vec!["bidder", "other"], bidders);
+
+        let msg = QueryMsg::OffersByBidder {
+            bidder: "other".into(),
+            start_after: Some(("nft-collectibles".into(), "1".into())),
+            limit: None,
         };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: TokenOffersResponse = from_binary(&res).unwrap();
+        assert_eq!(1, value.offers.len());
+        assert_eq!("2", value.offers[0].token_id);
+        assert_eq!(Price::from(coin(200, "earth")), value.offers[0].price);
 
+        // only listings may omit the msg tag
         let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
             token_id: "1".into(),
             sender: "owner".into(),
-            msg: to_binary(&sell_msg).unwrap(),
+            msg: to_binary(&AcceptOfferNft {
+                bidder: "bidder".into(),
+            })
+            .unwrap(),
         });
         let info = mock_info("nft-collectibles", &[]);
-        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone()).unwrap();
-        assert_eq!(0, res.messages.len());
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap_err();
 
-        let msg = ExecuteMsg::Buy {
-            offering_id: "1".into(),
+        // the owner sends the token to accept an offer
+        let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+            token_id: "1".into(),
+            sender: "owner".into(),
+            msg: to_binary(&ReceiveNftMsg::AcceptOffer(AcceptOfferNft {
+                bidder: "bidder".into(),
+            }))
+            .unwrap(),
+        });
+        let info = mock_info("nft-collectibles", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert_eq!(
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "owner".into(),
+                    amount: coins(490, "earth"),
+                }),
+                SubMsg::new(
+                    transfer_nft(
+                        &Addr::unchecked("nft-collectibles"),
+                        &Addr::unchecked("bidder"),
+                        "1"
+                    )
+                    .unwrap()
+                ),
+            ],
+            res.messages
+        );
+
+        // or accepts through an approval
+        let msg = ExecuteMsg::AcceptOffer {
+            nft_contract: "nft-collectibles".into(),
+            token_id: "2".into(),
+            bidder: "other".into(),
         };
-        let info = mock_info("owner", &coins(1000, "earth"));
-        let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::Unauthorized {}) => {}
+            _ => panic!("Must return Unauthorized error"),
+        }
+        let res = execute(
+            deps.as_mut(),
+            expired.clone(),
+            mock_info("owner", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::OfferExpired {}) => {}
+            _ => panic!("Must return OfferExpired error"),
+        }
+        let res = execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
+        assert_eq!(2, res.messages.len());
+        assert!(res.attributes.contains(&attr("price", "200earth")));
+
+        // expired offers can be refunded by anyone
+        let msg = ExecuteMsg::CancelOffer {
+            nft_contract: "nft-collectibles".into(),
+            token_id: "1".into(),
+            bidder: Some("other".into()),
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::OfferNotExpired {}) => {}
+            _ => panic!("Must return OfferNotExpired error"),
+        }
+        let res = execute(deps.as_mut(), expired, mock_info("anyone", &[]), msg).unwrap();
+        assert_eq!(
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "other".into(),
+                amount: coins(300, "earth"),
+            })],
+            res.messages
+        );
+
+        let msg = QueryMsg::OffersByToken {
+            nft_contract: "nft-collectibles".into(),
+            token_id: "1".into(),
+            start_after: None,
+            limit: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: TokenOffersResponse = from_binary(&res).unwrap();
+        assert!(value.offers.is_empty());
+    }
+
+    #[test]
+    fn cw20_offer_does_not_replace() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        let expires = mock_env().block.time.plus_seconds(100);
+        let info = mock_info("bidder", &coins(100, "earth"));
+        let msg = ExecuteMsg::MakeOffer {
+            nft_contract: "nft-collectibles".into(),
+            token_id: "1".into(),
+            expires,
+        };
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+
+        let offer = |token_id: &str| {
+            ExecuteMsg::Receive(Cw20ReceiveMsg {
+                sender: "bidder".into(),
+                amount: 100u128.into(),
+                msg: to_binary(&ReceiveMsg::MakeOffer {
+                    nft_contract: "nft-collectibles".into(),
+                    token_id: token_id.into(),
+                    expires,
+                })
+                .unwrap(),
+            })
+        };
+
+        // the escrowed offer is kept
+        let info = mock_info("fake", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), offer("1"));
+        match res {
+            Err(ContractError::OfferExists {}) => {}
+            _ => panic!("Must return OfferExists error"),
+        }
+        let key = (
+            &Addr::unchecked("nft-collectibles"),
+            "1",
+            &Addr::unchecked("bidder"),
+        );
+        let stored = crate::state::token_offers()
+            .load(&deps.storage, key)
+            .unwrap();
+        assert_eq!(Price::from(coin(100, "earth")), stored.price);
+
+        execute(deps.as_mut(), mock_env(), info.clone(), offer("2")).unwrap();
+        let res = execute(deps.as_mut(), mock_env(), info, offer("2"));
+        match res {
+            Err(ContractError::OfferExists {}) => {}
+            _ => panic!("Must return OfferExists error"),
+        }
+    }
+
+    #[test]
+    fn collection_bids() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        let expires = mock_env().block.time.plus_seconds(100);
+        let bid = |price: u128, quantity: u32| ExecuteMsg::MakeCollectionBid {
+            nft_contract: "nft-collectibles".into(),
+            price: price.into(),
+            quantity,
+            expires,
+        };
+        let info = mock_info("bidder", &coins(150, "earth"));
+        let res = execute(deps.as_mut(), mock_env(), info, bid(100, 2));
+        match res {
+            Err(ContractError::InvalidFunds {}) => {}
+            _ => panic!("Must return InvalidFunds error"),
+        }
+        let info = mock_info("bidder", &coins(200, "earth"));
+        execute(deps.as_mut(), mock_env(), info, bid(100, 2)).unwrap();
+        let info = mock_info("other", &coins(150, "earth"));
+        execute(deps.as_mut(), mock_env(), info, bid(150, 1)).unwrap();
+        let info = mock_info("other", &coins(300, "moon"));
+        execute(deps.as_mut(), mock_env(), info, bid(300, 1)).unwrap();
+
+        let top_bids = |deps: Deps| {
+            let msg = QueryMsg::TopCollectionBids {
+                nft_contract: "nft-collectibles".into(),
+                denom: "earth".into(),
+                start_after: None,
+                limit: None,
+            };
+            let res = query(deps, mock_env(), msg).unwrap();
+            let value: CollectionBidsResponse = from_binary(&res).unwrap();
+            value
+                .bids
+                .into_iter()
+                .map(|bid| (bid.id, bid.quantity))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            vec![("2".to_string(), 1), ("1".to_string(), 2)],
+            top_bids(deps.as_ref())
+        );
+
+        let msg = QueryMsg::TopCollectionBids {
+            nft_contract: "nft-collectibles".into(),
+            denom: "earth".into(),
+            start_after: None,
+            limit: Some(1),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: CollectionBidsResponse = from_binary(&res).unwrap();
+        assert_eq!("2", value.bids[0].id);
+        assert_eq!(
+            Some((Uint128::new(150), "2".to_string())),
+            value.next_start_after
+        );
+        let msg = QueryMsg::TopCollectionBids {
+            nft_contract: "nft-collectibles".into(),
+            denom: "earth".into(),
+            start_after: value.next_start_after,
+            limit: Some(1),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: CollectionBidsResponse = from_binary(&res).unwrap();
+        assert_eq!("1", value.bids[0].id);
+        assert_eq!(None, value.next_start_after);
+
+        // holders fill the bid one token at a time
+        let fill = |sender: &str, token_id: &str, bid_id: &str| {
+            ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+                token_id: token_id.into(),
+                sender: sender.into(),
+                msg: to_binary(&ReceiveNftMsg::FillCollectionBid(FillCollectionBid {
+                    bid_id: bid_id.into(),
+                }))
+                .unwrap(),
+            })
+        };
+        let info = mock_info("other-collection", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info, fill("alice", "1", "1"));
+        match res {
+            Err(ContractError::InvalidToken {}) => {}
+            _ => panic!("Must return InvalidToken error"),
+        }
+        let info = mock_info("nft-collectibles", &[]);
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            fill("alice", "1", "1"),
+        )
+        .unwrap();
+        assert_eq!(
+            vec![
+                SubMsg::new(BankMsg::Send {
+                    to_address: "alice".into(),
+                    amount: coins(98, "earth"),
+                }),
+                SubMsg::new(
+                    transfer_nft(
+                        &Addr::unchecked("nft-collectibles"),
+                        &Addr::unchecked("bidder"),
+                        "1"
+                    )
+                    .unwrap()
+                ),
+            ],
+            res.messages
+        );
+        assert!(res.attributes.contains(&attr("remaining", "1")));
+
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            info.clone(),
+            fill("bidder", "2", "1"),
+        );
         match res {
             Err(ContractError::InvalidBuyer {}) => {}
             _ => panic!("Must return InvalidBuyer error"),
         }
-
-        let info = mock_info("anyone", &coins(400, "earth"));
-        let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+        let mut expired = mock_env();
+        expired.block.time = expires;
+        let res = execute(
+            deps.as_mut(),
+            expired.clone(),
+            info.clone(),
+            fill("bob", "2", "1"),
+        );
         match res {
-            Err(ContractError::InsufficientFunds {}) => {}
-            _ => panic!("Must return InsufficientFunds error"),
+            Err(ContractError::OfferExpired {}) => {}
+            _ => panic!("Must return OfferExpired error"),
         }
+        execute(deps.as_mut(), mock_env(), info, fill("bob", "2", "1")).unwrap();
+        assert_eq!(vec![("2".to_string(), 1)], top_bids(deps.as_ref()));
 
-        let info = mock_info("anyone", &coins(1000, "earth"));
-        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        // the unfilled part is refunded
+        let msg = ExecuteMsg::CancelCollectionBid { bid_id: "2".into() };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            msg.clone(),
+        );
+        match res {
+            Err(ContractError::OfferNotExpired {}) => {}
+            _ => panic!("Must return OfferNotExpired error"),
+        }
+        let res = execute(deps.as_mut(), mock_env(), mock_info("other", &[]), msg).unwrap();
+        assert_eq!(
+            vec![SubMsg::new(BankMsg::Send {
+                to_address: "other".into(),
+                amount: coins(150, "earth"),
+            })],
+            res.messages
+        );
+        assert!(top_bids(deps.as_ref()).is_empty());
 
-        assert_eq!(2, res.messages.len());
+        // equal prices list the newest bid first
+        let info = mock_info("bidder", &coins(100, "earth"));
+        execute(deps.as_mut(), mock_env(), info.clone(), bid(100, 1)).unwrap();
+        execute(deps.as_mut(), mock_env(), info, bid(100, 1)).unwrap();
         assert_eq!(
-            res.messages[0],
-            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-                to_address: "owner".into(),
-                amount: coins(980, "earth")
-            }))
+            vec![("5".to_string(), 1), ("4".to_string(), 1)],
+            top_bids(deps.as_ref())
         );
     }
 
     #[test]
-    fn withdraw_fees() {
-        let mut deps = mock_dependencies_with_balance(&coins(1000, "earth"));
+    fn update_price_and_expiry() {
+        let mut deps = mock_dependencies();
         setup(deps.as_mut());
 
-        let msg = ExecuteMsg::WithdrawFees {
-            amount: 1000u32.into(),
-            denom: "earth".into(),
+        let now = mock_env().block.time;
+        let mut expired = mock_env();
+        expired.block.time = now.plus_seconds(100);
+        let sell = |token_id: &str, expires| {
+            ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+                token_id: token_id.into(),
+                sender: "owner".into(),
+                msg: to_binary(&SellNft {
+                    list_price: coin(1000, "earth").into(),
+                    pricing: None,
+                    expires: Some(expires),
+                })
+                .unwrap(),
+            })
         };
-        let info = mock_info("anyone", &[]);
-        let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+        let info = mock_info("nft-collectibles", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), sell("1", now));
+        match res {
+            Err(ContractError::InvalidExpiration {}) => {}
+            _ => panic!("Must return InvalidExpiration error"),
+        }
+        let expires = now.plus_seconds(100);
+        execute(deps.as_mut(), mock_env(), info.clone(), sell("1", expires)).unwrap();
+        execute(deps.as_mut(), mock_env(), info, sell("2", expires)).unwrap();
+
+        // the seller changes the price without relisting
+        let msg = ExecuteMsg::UpdatePrice {
+            offering_id: "1".into(),
+            list_price: coin(500, "earth").into(),
+        };
+        let res = execute(
+            deps.as_mut(),
+            mock_env(),
+            mock_info("anyone", &[]),
+            msg.clone(),
+        );
         match res {
             Err(ContractError::Unauthorized {}) => {}
             _ => panic!("Must return Unauthorized error"),
         }
+        execute(deps.as_mut(), mock_env(), mock_info("owner", &[]), msg).unwrap();
 
-        let info = mock_info("creator", &[]);
+        let msg = ExecuteMsg::Buy {
+            offering_id: "1".into(),
+        };
+        let info = mock_info("anyone", &coins(500, "earth"));
         let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(1, res.messages.len());
         assert_eq!(
-            res.messages[0],
-            SubMsg::new(CosmosMsg::Bank(BankMsg::Send {
-                to_address: "creator".into(),
-                amount: coins(1000, "earth")
-            }))
+            SubMsg::new(BankMsg::Send {
+                to_address: "owner".into(),
+                amount: coins(490, "earth"),
+            }),
+            res.messages[0]
+        );
+
+        // expired listings cannot be bought and are returned by anyone
+        let msg = ExecuteMsg::Buy {
+            offering_id: "2".into(),
+        };
+        let info = mock_info("anyone", &coins(1000, "earth"));
+        let res = execute(deps.as_mut(), expired.clone(), info, msg);
+        match res {
+            Err(ContractError::ListingExpired {}) => {}
+            _ => panic!("Must return ListingExpired error"),
+        }
+        let msg = ExecuteMsg::ReturnExpired {
+            offering_id: "2".into(),
+        };
+        let info = mock_info("anyone", &[]);
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), msg.clone());
+        match res {
+            Err(ContractError::ListingNotExpired {}) => {}
+            _ => panic!("Must return ListingNotExpired error"),
+        }
+        let res = execute(deps.as_mut(), expired, info, msg).unwrap();
+        assert_eq!(
+            vec![SubMsg::new(
+                transfer_nft(
+                    &Addr::unchecked("nft-collectibles"),
+                    &Addr::unchecked("owner"),
+                    "2"
+                )
+                .unwrap()
+            )],
+            res.messages
         );
+
+        let msg = QueryMsg::AllOffers {
+            start_after: None,
+            limit: None,
+            order: None,
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: OffersResponse = from_binary(&res).unwrap();
+        assert!(value.offers.is_empty());
     }
 
     #[test]
-    fn change_fee() {
+    fn indexed_offer_queries() {
         let mut deps = mock_dependencies();
         setup(deps.as_mut());
 
-        let msg = ExecuteMsg::ChangeFee {
-            fee: Decimal::percent(3),
+        for (seller, nft_contract, token_id) in [
+            ("owner", "nft-a", "1"),
+            ("owner", "nft-b", "2"),
+            ("other", "nft-a", "3"),
+        ] {
+            let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+                token_id: token_id.into(),
+                sender: seller.into(),
+                msg: to_binary(&SellNft {
+                    list_price: coin(1000, "earth").into(),
+                    pricing: None,
+                    expires: None,
+                })
+                .unwrap(),
+            });
+            let info = mock_info(nft_contract, &[]);
+            execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        }
+        let ids = |msg: QueryMsg, deps: Deps| {
+            let res = query(deps, mock_env(), msg).unwrap();
+            let value: OffersResponse = from_binary(&res).unwrap();
+            value.offers.into_iter().map(|o| o.id).collect::<Vec<_>>()
         };
-        let info = mock_info("anyone", &[]);
-        let res = execute(deps.as_mut(), mock_env(), info, msg.clone());
+
+        let msg = QueryMsg::OffersBySeller {
+            seller: "owner".into(),
+            start_after: None,
+            limit: None,
+            order: None,
+        };
+        assert_eq!(vec!["1", "2"], ids(msg, deps.as_ref()));
+        let msg = QueryMsg::OffersByCollection {
+            nft_contract: "nft-a".into(),
+            start_after: None,
+            limit: None,
+            order: None,
+        };
+        assert_eq!(vec!["1", "3"], ids(msg, deps.as_ref()));
+        let msg = QueryMsg::OffersByCollection {
+            nft_contract: "nft-a".into(),
+            start_after: Some("1".into()),
+            limit: Some(1),
+            order: None,
+        };
+        assert_eq!(vec!["3"], ids(msg, deps.as_ref()));
+
+        let offer_by_token = |deps: Deps, nft_contract: &str, token_id: &str| {
+            let msg = QueryMsg::OfferByToken {
+                nft_contract: nft_contract.into(),
+                token_id: token_id.into(),
+            };
+            let res = query(deps, mock_env(), msg).unwrap();
+            from_binary::<OfferResponse>(&res).unwrap().offer
+        };
+        let offer = offer_by_token(deps.as_ref(), "nft-b", "2").unwrap();
+        assert_eq!("2", offer.id);
+        assert!(offer_by_token(deps.as_ref(), "nft-a", "2").is_none());
+
+        let msg = QueryMsg::Offer {
+            offering_id: "3".into(),
+        };
+        let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+        let value: Offer = from_binary(&res).unwrap();
+        assert_eq!("other", value.seller);
+
+        // sold offerings leave the indexes
+        let msg = ExecuteMsg::Buy {
+            offering_id: "1".into(),
+        };
+        let info = mock_info("anyone", &coins(1000, "earth"));
+        execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        assert!(offer_by_token(deps.as_ref(), "nft-a", "1").is_none());
+        let msg = QueryMsg::OffersByCollection {
+            nft_contract: "nft-a".into(),
+            start_after: None,
+            limit: None,
+            order: None,
+        };
+        assert_eq!(vec!["3"], ids(msg, deps.as_ref()));
+
+        // the string keyed offerings are moved and indexed in batches after migrate
+        let mut off = offerings().load(&deps.storage, 3).unwrap();
+        for id in ["4", "5"] {
+            off.token_id = id.into();
+            LEGACY_OFFERINGS.save(&mut deps.storage, id, &off).unwrap();
+        }
+        let legacy_index = Map::<Vec<u8>, Empty>::new(LEGACY_OFFERING_INDEXES[0]);
+        legacy_index
+            .save(&mut deps.storage, b"other4".to_vec(), &Empty {})
+            .unwrap();
+        assert!(offer_by_token(deps.as_ref(), "nft-a", "4").is_none());
+        migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+
+        let buy = ExecuteMsg::Buy {
+            offering_id: "3".into(),
+        };
+        let info = mock_info("anyone", &coins(1000, "earth"));
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), buy.clone());
         match res {
-            Err(ContractError::Unauthorized {}) => {}
-            _ => panic!("Must return Unauthorized error"),
+            Err(ContractError::MigrationPending {}) => {}
+            _ => panic!("Must return MigrationPending error"),
+        }
+        let batch = ExecuteMsg::MigrateOfferings { limit: Some(1) };
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), batch.clone()).unwrap();
+        assert_eq!(attr("offerings", "1"), res.attributes[1]);
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), buy.clone());
+        match res {
+            Err(ContractError::MigrationPending {}) => {}
+            _ => panic!("Must return MigrationPending error"),
         }
+        execute(deps.as_mut(), mock_env(), info.clone(), batch.clone()).unwrap();
+        assert!(LEGACY_OFFERINGS.is_empty(&deps.storage));
+        let res = execute(deps.as_mut(), mock_env(), info.clone(), batch).unwrap();
+        assert_eq!(attr("index_entries", "1"), res.attributes[2]);
+        assert!(legacy_index.is_empty(&deps.storage));
 
-        let info = mock_info("creator", &[]);
-        let res = execute(deps.as_mut(), mock_env(), info, msg).unwrap();
-        assert_eq!(0, res.messages.len());
+        let msg = QueryMsg::OffersBySeller {
+            seller: "other".into(),
+            start_after: None,
+            limit: None,
+            order: None,
+        };
+        assert_eq!(vec!["3", "4", "5"], ids(msg, deps.as_ref()));
+        assert_eq!("4", offer_by_token(deps.as_ref(), "nft-a", "4").unwrap().id);
+        execute(deps.as_mut(), mock_env(), info, buy).unwrap();
 
-        let msg = QueryMsg::GetFee {};
+        // only this contract can be migrated
+        set_contract_version(&mut deps.storage, "crates.io:other", "0.1.0").unwrap();
+        let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {});
+        match res {
+            Err(ContractError::InvalidMigration { contract }) => {
+                assert_eq!("crates.io:other", contract)
+            }
+            _ => panic!("Must return InvalidMigration error"),
+        }
+    }
+
+    #[test]
+    fn offering_pagination() {
+        let mut deps = mock_dependencies();
+        setup(deps.as_mut());
+
+        for token_id in 1..=11 {
+            let msg = ExecuteMsg::ReceiveNft(Cw721ReceiveMsg {
+                token_id: token_id.to_string(),
+                sender: "owner".into(),
+                msg: to_binary(&SellNft {
+                    list_price: coin(1000, "earth").into(),
+                    pricing: None,
+                    expires: None,
+                })
+                .unwrap(),
+            });
+            let info = mock_info("nft-collectibles", &[]);
+            execute(deps.as_mut(), mock_env(), info, msg).unwrap();
+        }
+        let ids = |start_after: Option<&str>, order: Option<OrderBy>| {
+            let msg = QueryMsg::AllOffers {
+                start_after: start_after.map(String::from),
+                limit: Some(3),
+                order,
+            };
+            let res = query(deps.as_ref(), mock_env(), msg).unwrap();
+            let value: OffersResponse = from_binary(&res).unwrap();
+            value.offers.into_iter().map(|o| o.id).collect::<Vec<_>>()
+        };
+
+        // ids are ordered numerically
+        assert_eq!(vec!["1", "2", "3"], ids(None, None));
+        assert_eq!(vec!["10", "11"], ids(Some("9"), None));
+        assert_eq!(vec!["11", "10", "9"], ids(None, Some(OrderBy::Descending)));
+        assert_eq!(
+            vec!["9", "8", "7"],
+            ids(Some("10"), Some(OrderBy::Descending))
+        );
+
+        let msg = QueryMsg::OffersBySeller {
+            seller: "owner".into(),
+            start_after: Some("3".into()),
+            limit: None,
+            order: Some(OrderBy::Descending),
+        };
         let res = query(deps.as_ref(), mock_env(), msg).unwrap();
-        let value: FeeResponse = from_binary(&res).unwrap();
-        assert_eq!(Decimal::percent(3), value.fee);
+        let value: OffersResponse = from_binary(&res).unwrap();
+        assert_eq!(
+            vec!["2", "1"],
+            value
+                .offers
+                .iter()
+                .map(|o| o.id.as_str())
+                .collect::<Vec<_>>()
+        );
     }
 }
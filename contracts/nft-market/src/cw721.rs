@@ -1,6 +1,8 @@
 use cosmwasm_schema::cw_serde;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
-use cosmwasm_std::Binary;
+use cosmwasm_std::{Binary, Uint128};
 
 #[cw_serde]
 pub enum Cw721ExecuteMsg {
@@ -13,3 +15,37 @@ pub struct Cw721ReceiveMsg {
     pub token_id: String,
     pub msg: Binary,
 }
+
+#[cw_serde]
+pub enum Cw721QueryMsg {
+    OwnerOf {
+        token_id: String,
+        include_expired: Option<bool>,
+    },
+}
+
+/// Owner of a token, other fields of the response are ignored
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct OwnerOfResponse {
+    pub owner: String,
+}
+
+/// CW2981 royalties extension query of a cw721 contract
+#[cw_serde]
+pub enum Cw2981QueryMsg {
+    Extension { msg: Cw2981Extension },
+}
+
+#[cw_serde]
+pub enum Cw2981Extension {
+    RoyaltyInfo {
+        token_id: String,
+        sale_price: Uint128,
+    },
+}
+
+#[cw_serde]
+pub struct RoyaltiesInfoResponse {
+    pub address: String,
+    pub royalty_amount: Uint128,
+}
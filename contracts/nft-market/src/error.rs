@@ -1,4 +1,4 @@
-use cosmwasm_std::StdError;
+use cosmwasm_std::{Decimal, StdError, Uint128};
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -16,6 +16,60 @@ pub enum ContractError {
     #[error("Invalid buyer")]
     InvalidBuyer {},
 
+    #[error("Send exactly one coin")]
+    InvalidFunds {},
+
+    #[error("Offer expiration must be in the future")]
+    InvalidExpiration {},
+
+    #[error("Offer has expired")]
+    OfferExpired {},
+
+    #[error("Offer has not expired")]
+    OfferNotExpired {},
+
+    #[error("Cancel the existing offer first")]
+    OfferExists {},
+
+    #[error("Listing has expired")]
+    ListingExpired {},
+
+    #[error("Listing has not expired")]
+    ListingNotExpired {},
+
+    #[error("Payment token does not match the listing price")]
+    InvalidToken {},
+
+    #[error("Fee must not exceed {max}")]
+    InvalidFee { max: Decimal },
+
+    #[error("Withdraw amount must be greater than zero")]
+    InvalidWithdrawAmount {},
+
+    #[error("Dutch floor price must not exceed the list price and end after the start time")]
+    InvalidPricing {},
+
+    #[error("Auction end time must be in the future and after the start time")]
+    InvalidAuction {},
+
+    #[error("Auction minimum increment must be greater than zero")]
+    InvalidIncrement {},
+
+    #[error("Auction is not active")]
+    AuctionNotActive {},
+
+    #[error("Auction has not ended")]
+    AuctionNotEnded {},
+
+    #[error("Bid must be at least {min}")]
+    BidTooLow { min: Uint128 },
+
     #[error("No data in ReceiveMsg")]
     NoData {},
+
+    #[error("Cannot migrate from {contract}")]
+    InvalidMigration { contract: String },
+
+    #[error("Offerings are being migrated, send MigrateOfferings first")]
+    MigrationPending {},
 }
@@ -1,12 +1,21 @@
 use crate::cw721::Cw721ReceiveMsg;
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Coin, Decimal, Uint128};
+use cosmwasm_std::{Addr, Coin, Decimal, Timestamp, Uint128};
+use cw20::{Cw20Coin, Cw20ReceiveMsg};
+use std::fmt;
 
 #[cw_serde]
 pub struct InstantiateMsg {
     pub fee: Decimal,
+    /// Upper bound of the fee, at most 1 (default: 1)
+    pub max_fee: Option<Decimal>,
+    /// Delay in seconds before a fee increase takes effect (default: 0)
+    pub fee_timelock: Option<u64>,
 }
 
+#[cw_serde]
+pub struct MigrateMsg {}
+
 #[cw_serde]
 pub enum ExecuteMsg {
     Buy {
@@ -15,21 +24,209 @@ pub enum ExecuteMsg {
     WithdrawNft {
         offering_id: String,
     },
+    /// Change the price of an offering, only seller. Keeps the Dutch schedule
+    UpdatePrice {
+        offering_id: String,
+        list_price: Price,
+    },
+    /// Return an expired offering to its seller, callable by anyone
+    ReturnExpired {
+        offering_id: String,
+    },
     ReceiveNft(Cw721ReceiveMsg),
-    /// only admin.
+    /// Buy offerings or bid on auctions with cw20 tokens
+    Receive(Cw20ReceiveMsg),
+    /// Bid on an auction with native funds, the previous highest bid is refunded
+    PlaceBid {
+        auction_id: String,
+    },
+    /// Transfer the NFT and proceeds of an ended auction, anyone can settle
+    SettleAuction {
+        auction_id: String,
+    },
+    /// Offer to buy a token with the sent funds, escrowed until accepted, cancelled or replaced
+    MakeOffer {
+        nft_contract: String,
+        token_id: String,
+        expires: Timestamp,
+    },
+    /// Refund an offer, by the bidder at any time or by anyone once expired
+    CancelOffer {
+        nft_contract: String,
+        token_id: String,
+        bidder: Option<String>,
+    },
+    /// Accept an offer as the token owner, the market must be approved to transfer the token
+    AcceptOffer {
+        nft_contract: String,
+        token_id: String,
+        bidder: String,
+    },
+    /// Bid on any token of a collection, escrowing `price` times `quantity` of the sent coin
+    MakeCollectionBid {
+        nft_contract: String,
+        price: Uint128,
+        quantity: u32,
+        expires: Timestamp,
+    },
+    /// Refund the unfilled part of a collection bid, by the bidder at any time or by anyone once expired
+    CancelCollectionBid {
+        bid_id: String,
+    },
+    /// only admin. Withdraw accrued fees of a native denom or cw20 address,
+    /// recipient defaults to the admin
     WithdrawFees {
         amount: Uint128,
         denom: String,
+        recipient: Option<String>,
+    },
+    /// only admin. Withdraw all accrued fees
+    WithdrawAllFees {
+        recipient: Option<String>,
     },
-    /// only admin.
+    /// only admin. Fee increases take effect after the fee timelock
     ChangeFee {
         fee: Decimal,
     },
+    /// Move a batch of offerings of a market deployed before the u64 ids, anyone can call.
+    /// Other msgs fail until no legacy offering is left
+    MigrateOfferings {
+        limit: Option<u32>,
+    },
+}
+
+#[cw_serde]
+pub enum ReceiveMsg {
+    Buy {
+        offering_id: String,
+    },
+    PlaceBid {
+        auction_id: String,
+    },
+    MakeOffer {
+        nft_contract: String,
+        token_id: String,
+        expires: Timestamp,
+    },
+    MakeCollectionBid {
+        nft_contract: String,
+        price: Uint128,
+        quantity: u32,
+        expires: Timestamp,
+    },
+}
+
+/// Msg sent with the NFT: a fixed price listing, an auction, or a sale to an offer or collection bid
+#[cw_serde]
+pub enum ReceiveNftMsg {
+    Sell(SellNft),
+    Auction(AuctionNft),
+    AcceptOffer(AcceptOfferNft),
+    FillCollectionBid(FillCollectionBid),
+}
+
+/// A tagged `ReceiveNftMsg`, or a bare `SellNft` as sent before the tags
+#[cw_serde]
+#[serde(untagged)]
+pub enum ReceiveNftPayload {
+    Msg(ReceiveNftMsg),
+    LegacySell(SellNft),
 }
 
 #[cw_serde]
 pub struct SellNft {
-    pub list_price: Coin,
+    /// Fixed price, or start price of a Dutch listing
+    pub list_price: Price,
+    /// Fixed by default
+    pub pricing: Option<Pricing>,
+    /// The offering cannot be bought after this time, never expires by default
+    pub expires: Option<Timestamp>,
+}
+
+/// Accept the offer of the bidder on the sent token
+#[cw_serde]
+pub struct AcceptOfferNft {
+    pub bidder: String,
+}
+
+/// Sell the sent token to a collection bid
+#[cw_serde]
+pub struct FillCollectionBid {
+    pub bid_id: String,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum Pricing {
+    #[default]
+    Fixed,
+    /// Price decays linearly from the list price to the floor price between start and end time
+    Dutch {
+        floor_price: Uint128,
+        start_time: Timestamp,
+        end_time: Timestamp,
+    },
+}
+
+#[cw_serde]
+pub struct AuctionNft {
+    /// Minimum first bid, also defines the bid token
+    pub reserve_price: Price,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+    /// Minimum raise over the highest bid
+    pub min_increment: Uint128,
+    /// Bids placed in the last seconds extend the end time by this period
+    pub extension: Option<u64>,
+}
+
+/// Listing price in a native coin `{"denom", "amount"}`
+/// or a cw20 token `{"address", "amount"}`
+#[cw_serde]
+#[serde(untagged)]
+pub enum Price {
+    Native(Coin),
+    Cw20(Cw20Coin),
+}
+
+impl Price {
+    pub fn amount(&self) -> Uint128 {
+        match self {
+            Price::Native(coin) => coin.amount,
+            Price::Cw20(coin) => coin.amount,
+        }
+    }
+
+    /// Native denom or cw20 address
+    pub fn denom(&self) -> &str {
+        match self {
+            Price::Native(coin) => &coin.denom,
+            Price::Cw20(coin) => &coin.address,
+        }
+    }
+
+    /// Same token with another amount
+    pub fn with_amount(&self, amount: Uint128) -> Price {
+        match self {
+            Price::Native(coin) => Price::Native(Coin::new(amount.u128(), &coin.denom)),
+            Price::Cw20(coin) => Price::Cw20(Cw20Coin {
+                address: coin.address.clone(),
+                amount,
+            }),
+        }
+    }
+}
+
+impl From<Coin> for Price {
+    fn from(coin: Coin) -> Self {
+        Price::Native(coin)
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}{}", self.amount(), self.denom())
+    }
 }
 
 #[cw_serde]
@@ -39,6 +236,9 @@ pub enum QueryMsg {
     GetCount {},
     #[returns(FeeResponse)]
     GetFee {},
+    /// Fee increase waiting for the timelock
+    #[returns(PendingFeeResponse)]
+    PendingFee {},
     /// With Enumerable extension.
     /// Requires pagination. Lists all offers controlled by the contract.
     /// Return type: OffersResponse.
@@ -46,6 +246,82 @@ pub enum QueryMsg {
     AllOffers {
         start_after: Option<String>,
         limit: Option<u32>,
+        order: Option<OrderBy>,
+    },
+    #[returns(Offer)]
+    Offer { offering_id: String },
+    /// Offerings of a seller. Supports pagination
+    #[returns(OffersResponse)]
+    OffersBySeller {
+        seller: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        order: Option<OrderBy>,
+    },
+    /// Offerings of an NFT contract. Supports pagination
+    #[returns(OffersResponse)]
+    OffersByCollection {
+        nft_contract: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+        order: Option<OrderBy>,
+    },
+    /// Offering of a token, if listed
+    #[returns(OfferResponse)]
+    OfferByToken {
+        nft_contract: String,
+        token_id: String,
+    },
+    /// Price of the offering at the current block
+    #[returns(CurrentPriceResponse)]
+    CurrentPrice { offering_id: String },
+    /// Offers on a token. Supports pagination
+    #[returns(TokenOffersResponse)]
+    OffersByToken {
+        nft_contract: String,
+        token_id: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Offers of a bidder, `start_after` is an (nft_contract, token_id) pair. Supports pagination
+    #[returns(TokenOffersResponse)]
+    OffersByBidder {
+        bidder: String,
+        start_after: Option<(String, String)>,
+        limit: Option<u32>,
+    },
+    #[returns(CollectionBidInfo)]
+    CollectionBid { bid_id: String },
+    /// Highest unexpired collection bids in a native denom or cw20 address.
+    /// Equal prices list the newest bid first. Expired bids are skipped, so a page
+    /// may hold fewer than `limit` bids, keep paging with `next_start_after`
+    #[returns(CollectionBidsResponse)]
+    TopCollectionBids {
+        nft_contract: String,
+        denom: String,
+        /// (price, bid id) of the last bid read
+        start_after: Option<(Uint128, String)>,
+        limit: Option<u32>,
+    },
+    /// Marketplace fees available to withdraw
+    #[returns(AccruedFeesResponse)]
+    AccruedFees {},
+    #[returns(AuctionInfo)]
+    Auction { auction_id: String },
+    /// Auctions not ended yet. Supports pagination.
+    /// Ended auctions awaiting settlement are skipped, so a page may hold
+    /// fewer than `limit` auctions, keep paging with `next_start_after`
+    #[returns(AuctionsResponse)]
+    ActiveAuctions {
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    /// Bids of an auction, oldest first. Supports pagination
+    #[returns(BidsResponse)]
+    BidHistory {
+        auction_id: String,
+        start_after: Option<u32>,
+        limit: Option<u32>,
     },
 }
 
@@ -57,6 +333,29 @@ pub struct CountResponse {
 #[cw_serde]
 pub struct FeeResponse {
     pub fee: Decimal,
+    pub max_fee: Decimal,
+    pub fee_timelock: u64,
+}
+
+#[cw_serde]
+pub struct PendingFee {
+    pub fee: Decimal,
+    pub effective_at: Timestamp,
+}
+
+#[cw_serde]
+pub struct PendingFeeResponse {
+    pub pending: Option<PendingFee>,
+}
+
+#[cw_serde]
+pub struct AccruedFeesResponse {
+    pub fees: Vec<Price>,
+}
+
+#[cw_serde]
+pub struct CurrentPriceResponse {
+    pub price: Price,
 }
 
 #[cw_serde]
@@ -70,5 +369,87 @@ pub struct Offer {
     pub token_id: String,
     pub contract: Addr,
     pub seller: Addr,
-    pub list_price: Coin,
+    pub list_price: Price,
+    pub pricing: Pricing,
+    pub expires: Option<Timestamp>,
+}
+
+/// Order of offering ids, descending lists the newest offerings first
+#[cw_serde]
+#[derive(Default)]
+pub enum OrderBy {
+    #[default]
+    Ascending,
+    Descending,
+}
+
+#[cw_serde]
+pub struct OfferResponse {
+    pub offer: Option<Offer>,
+}
+
+#[cw_serde]
+pub struct Bid {
+    pub bidder: Addr,
+    pub amount: Uint128,
+    pub time: Timestamp,
+}
+
+#[cw_serde]
+pub struct AuctionInfo {
+    pub id: String,
+    pub token_id: String,
+    pub contract: Addr,
+    pub seller: Addr,
+    pub reserve_price: Price,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+    pub min_increment: Uint128,
+    pub extension: u64,
+    pub highest_bid: Option<Bid>,
+}
+
+#[cw_serde]
+pub struct AuctionsResponse {
+    pub auctions: Vec<AuctionInfo>,
+    /// Cursor to the next page, None once all auctions are read
+    pub next_start_after: Option<String>,
+}
+
+#[cw_serde]
+pub struct BidsResponse {
+    pub bids: Vec<Bid>,
+}
+
+#[cw_serde]
+pub struct TokenOffer {
+    pub nft_contract: Addr,
+    pub token_id: String,
+    pub bidder: Addr,
+    pub price: Price,
+    pub expires: Timestamp,
+}
+
+#[cw_serde]
+pub struct TokenOffersResponse {
+    pub offers: Vec<TokenOffer>,
+}
+
+#[cw_serde]
+pub struct CollectionBidInfo {
+    pub id: String,
+    pub nft_contract: Addr,
+    pub bidder: Addr,
+    /// Price per token
+    pub price: Price,
+    /// Tokens left to fill
+    pub quantity: u32,
+    pub expires: Timestamp,
+}
+
+#[cw_serde]
+pub struct CollectionBidsResponse {
+    pub bids: Vec<CollectionBidInfo>,
+    /// Cursor to the next page, None once all bids are read
+    pub next_start_after: Option<(Uint128, String)>,
 }
@@ -1,14 +1,23 @@
 use cosmwasm_schema::cw_serde;
 
 use crate::error::ContractError;
-use cosmwasm_std::{Addr, Api, Coin, Decimal, StdResult, Storage};
-use cw_storage_plus::{Item, Map};
+use crate::msg::{Bid, PendingFee, Price, Pricing, TokenOffer};
+use cosmwasm_std::{Addr, Api, Coin, Decimal, StdError, StdResult, Storage, Timestamp, Uint128};
+use cw_storage_plus::{Index, IndexList, IndexedMap, Item, Map, MultiIndex, UniqueIndex};
 
 #[cw_serde]
 pub struct State {
     pub num_offerings: u64,
     pub fee: Decimal,
     pub owner: Addr,
+    #[serde(default = "Decimal::one")]
+    pub max_fee: Decimal,
+    #[serde(default)]
+    pub fee_timelock: u64,
+    #[serde(default)]
+    pub num_auctions: u64,
+    #[serde(default)]
+    pub num_collection_bids: u64,
 }
 
 #[cw_serde]
@@ -16,11 +25,195 @@ pub struct Offering {
     pub token_id: String,
     pub contract: Addr,
     pub seller: Addr,
-    pub list_price: Coin,
+    pub list_price: Price,
+    #[serde(default)]
+    pub pricing: Pricing,
+    #[serde(default)]
+    pub expires: Option<Timestamp>,
+}
+
+impl Offering {
+    pub fn is_expired(&self, now: Timestamp) -> bool {
+        self.expires.is_some_and(|expires| now >= expires)
+    }
+
+    /// Price at the given time
+    pub fn current_price(&self, now: Timestamp) -> Price {
+        match &self.pricing {
+            Pricing::Fixed => self.list_price.clone(),
+            Pricing::Dutch {
+                floor_price,
+                start_time,
+                end_time,
+            } => {
+                let start_price = self.list_price.amount();
+                let amount = if now <= *start_time {
+                    start_price
+                } else if now >= *end_time {
+                    *floor_price
+                } else {
+                    let elapsed = now.seconds() - start_time.seconds();
+                    let duration = end_time.seconds() - start_time.seconds();
+                    start_price - (start_price - floor_price).multiply_ratio(elapsed, duration)
+                };
+                self.list_price.with_amount(amount)
+            }
+        }
+    }
+}
+
+#[cw_serde]
+pub struct Auction {
+    pub token_id: String,
+    pub contract: Addr,
+    pub seller: Addr,
+    pub reserve_price: Price,
+    pub start_time: Timestamp,
+    pub end_time: Timestamp,
+    pub min_increment: Uint128,
+    pub extension: u64,
+    pub highest_bid: Option<Bid>,
+    pub num_bids: u32,
 }
 
 pub const STATE: Item<State> = Item::new("state");
-pub const OFFERINGS: Map<&str, Offering> = Map::new("offerings");
+pub const PENDING_FEE: Item<PendingFee> = Item::new("pending_fee");
+
+pub struct OfferingIndexes<'a> {
+    pub seller: MultiIndex<'a, Addr, Offering, u64>,
+    pub contract: MultiIndex<'a, Addr, Offering, u64>,
+    /// The market holds the token, so it is listed at most once
+    pub token: UniqueIndex<'a, (Addr, String), Offering, u64>,
+}
+
+impl<'a> IndexList<Offering> for OfferingIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<Offering>> + '_> {
+        let v: Vec<&dyn Index<Offering>> = vec![&self.seller, &self.contract, &self.token];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Offerings by id, the API exposes the ids as strings
+pub fn offerings<'a>() -> IndexedMap<'a, u64, Offering, OfferingIndexes<'a>> {
+    let indexes = OfferingIndexes {
+        seller: MultiIndex::new(
+            |_pk, off| off.seller.clone(),
+            "offerings_v2",
+            "offerings_v2__seller",
+        ),
+        contract: MultiIndex::new(
+            |_pk, off| off.contract.clone(),
+            "offerings_v2",
+            "offerings_v2__contract",
+        ),
+        token: UniqueIndex::new(
+            |off| (off.contract.clone(), off.token_id.clone()),
+            "offerings_v2__token",
+        ),
+    };
+    IndexedMap::new("offerings_v2", indexes)
+}
+
+/// Offerings keyed by the string of their id, moved to `offerings` by `MigrateOfferings`
+pub const LEGACY_OFFERINGS: Map<&str, Offering> = Map::new("offerings");
+/// Indexes of the legacy offerings, cleared by `MigrateOfferings`
+pub const LEGACY_OFFERING_INDEXES: [&str; 3] = [
+    "offerings__seller",
+    "offerings__contract",
+    "offerings__token",
+];
+
+/// Parses an offering id of the API
+pub fn offering_key(offering_id: &str) -> StdResult<u64> {
+    offering_id
+        .parse()
+        .map_err(|_| StdError::not_found("Offering"))
+}
+
+/// Parses a collection bid id of the API
+pub fn collection_bid_key(bid_id: &str) -> StdResult<u64> {
+    bid_id
+        .parse()
+        .map_err(|_| StdError::not_found("CollectionBid"))
+}
+
+pub const AUCTIONS: Map<u64, Auction> = Map::new("auctions");
+pub const AUCTION_BIDS: Map<(u64, u32), Bid> = Map::new("auction_bids");
+
+/// Parses an auction id of the API
+pub fn auction_key(auction_id: &str) -> StdResult<u64> {
+    auction_id
+        .parse()
+        .map_err(|_| StdError::not_found("Auction"))
+}
+
+pub struct TokenOfferIndexes<'a> {
+    pub bidder: MultiIndex<'a, Addr, TokenOffer, (Addr, String, Addr)>,
+}
+
+impl<'a> IndexList<TokenOffer> for TokenOfferIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<TokenOffer>> + '_> {
+        let v: Vec<&dyn Index<TokenOffer>> = vec![&self.bidder];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Buyer offers by (nft contract, token id, bidder)
+pub fn token_offers<'a>(
+) -> IndexedMap<'a, (&'a Addr, &'a str, &'a Addr), TokenOffer, TokenOfferIndexes<'a>> {
+    let indexes = TokenOfferIndexes {
+        bidder: MultiIndex::new(
+            |_pk, offer| offer.bidder.clone(),
+            "token_offers",
+            "token_offers__bidder",
+        ),
+    };
+    IndexedMap::new("token_offers", indexes)
+}
+
+#[cw_serde]
+pub struct CollectionBid {
+    pub nft_contract: Addr,
+    pub bidder: Addr,
+    /// Price per token
+    pub price: Price,
+    /// Tokens left to fill
+    pub quantity: u32,
+    pub expires: Timestamp,
+}
+
+pub struct CollectionBidIndexes<'a> {
+    /// (nft contract, denom, price amount)
+    pub price: MultiIndex<'a, (Addr, String, u128), CollectionBid, u64>,
+}
+
+impl<'a> IndexList<CollectionBid> for CollectionBidIndexes<'a> {
+    fn get_indexes(&'_ self) -> Box<dyn Iterator<Item = &'_ dyn Index<CollectionBid>> + '_> {
+        let v: Vec<&dyn Index<CollectionBid>> = vec![&self.price];
+        Box::new(v.into_iter())
+    }
+}
+
+/// Collection bids by id, the API exposes the ids as strings
+pub fn collection_bids<'a>() -> IndexedMap<'a, u64, CollectionBid, CollectionBidIndexes<'a>> {
+    let indexes = CollectionBidIndexes {
+        price: MultiIndex::new(
+            |_pk, bid| {
+                (
+                    bid.nft_contract.clone(),
+                    bid.price.denom().to_string(),
+                    bid.price.amount().u128(),
+                )
+            },
+            "collection_bids",
+            "collection_bids__price",
+        ),
+    };
+    IndexedMap::new("collection_bids", indexes)
+}
+
+/// Accrued marketplace fees by native denom or cw20 address
+pub const FEES: Map<&str, Price> = Map::new("fees");
 
 pub fn increment_offerings(store: &mut dyn Storage) -> Result<u64, ContractError> {
     let mut num = 0;
@@ -46,3 +239,25 @@ pub fn get_fund(funds: Vec<Coin>, denom: String) -> Result<Coin, ContractError>
 pub fn maybe_addr(api: &dyn Api, human: Option<String>) -> StdResult<Option<Addr>> {
     human.map(|x| api.addr_validate(&x)).transpose()
 }
+
+pub fn increment_auctions(store: &mut dyn Storage) -> Result<u64, ContractError> {
+    let mut num = 0;
+    STATE.update(store, |mut state| -> Result<_, ContractError> {
+        state.num_auctions += 1;
+        num = state.num_auctions;
+        Ok(state)
+    })?;
+
+    Ok(num)
+}
+
+pub fn increment_collection_bids(store: &mut dyn Storage) -> Result<u64, ContractError> {
+    let mut num = 0;
+    STATE.update(store, |mut state| -> Result<_, ContractError> {
+        state.num_collection_bids += 1;
+        num = state.num_collection_bids;
+        Ok(state)
+    })?;
+
+    Ok(num)
+}